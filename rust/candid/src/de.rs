@@ -0,0 +1,236 @@
+//! Deserializing the Candid binary format into Rust values and [`IDLValue`]s.
+
+use crate::error::{Error, Result};
+use crate::types::CandidType;
+use crate::wire::{self, TypeTable, WireTypeTable};
+use std::io;
+
+/// A deserializer over a sequence of Candid argument values.
+///
+/// `R` is the underlying byte source. [`IDLDeserialize::new`] wraps an
+/// in-memory `&[u8]`; [`IDLDeserialize::from_reader`] instead pulls bytes
+/// incrementally from any `R: io::Read`, which matters for canisters that
+/// stream a large blob or vector off a socket rather than holding the whole
+/// message in memory up front.
+pub struct IDLDeserialize<R> {
+    reader: R,
+    table: WireTypeTable,
+    next_arg: usize,
+}
+
+impl IDLDeserialize<io::Cursor<Vec<u8>>> {
+    /// Parse the type table out of `bytes`, ready to decode its arguments.
+    pub fn new(bytes: &[u8]) -> Result<Self> {
+        Self::from_reader(io::Cursor::new(bytes.to_vec()))
+    }
+}
+
+impl<R: io::Read> IDLDeserialize<R> {
+    /// Buffer the type table off `reader`, then decode arguments from it on
+    /// demand as [`get_value`](Self::get_value) is called.
+    pub fn from_reader(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"DIDL" {
+            return Err(Error::msg("wrong magic number for a Candid message"));
+        }
+        let table = WireTypeTable::read(&mut reader)?;
+        Ok(IDLDeserialize {
+            reader,
+            table,
+            next_arg: 0,
+        })
+    }
+
+    /// Decode the next argument as `T`.
+    pub fn get_value<T: CandidType>(&mut self) -> Result<T> {
+        let ty_ref = *self
+            .table
+            .arg_refs
+            .get(self.next_arg)
+            .ok_or_else(|| Error::msg("no more arguments to decode"))?;
+        let ty = self.table.resolve(ty_ref)?;
+        let value = wire::read_value(&mut self.reader, &ty)?;
+        self.next_arg += 1;
+        T::from_idl_value(value)
+    }
+
+    /// Decode a single field of argument `arg_index` without materializing
+    /// the rest of it, e.g. `de.get_field::<u32>(0, &["config", "limit"])`.
+    ///
+    /// `path` walks down through nested `record`/`variant`/`opt` structure
+    /// (an empty path decodes the whole argument, like [`get_value`](Self::get_value)),
+    /// using the type table to skip every field `path` doesn't lead through
+    /// instead of decoding it, and decoding only the targeted leaf. Like
+    /// `get_value`, arguments before `arg_index` are skipped the first time
+    /// they're passed over and can't be revisited afterwards; if `path`
+    /// doesn't match the argument's shape this returns an error and leaves
+    /// the deserializer unusable, the same as a malformed message would.
+    pub fn get_field<T: CandidType>(&mut self, arg_index: usize, path: &[&str]) -> Result<T> {
+        if arg_index < self.next_arg {
+            return Err(Error::msg("argument has already been decoded"));
+        }
+        while self.next_arg < arg_index {
+            let ty_ref = *self
+                .table
+                .arg_refs
+                .get(self.next_arg)
+                .ok_or_else(|| Error::msg("no more arguments to decode"))?;
+            let ty = self.table.resolve(ty_ref)?;
+            wire::skip_value(&mut self.reader, &ty)?;
+            self.next_arg += 1;
+        }
+        let ty_ref = *self
+            .table
+            .arg_refs
+            .get(arg_index)
+            .ok_or_else(|| Error::msg("no more arguments to decode"))?;
+        let ty = self.table.resolve(ty_ref)?;
+        let value = wire::extract_field(&mut self.reader, &ty, path)?;
+        self.next_arg += 1;
+        T::from_idl_value(value)
+    }
+
+    /// Whether every argument has been decoded.
+    pub fn is_done(&self) -> bool {
+        self.next_arg >= self.table.arg_refs.len()
+    }
+
+    /// Assert that every argument has been decoded.
+    pub fn done(self) -> Result<()> {
+        if self.is_done() {
+            Ok(())
+        } else {
+            Err(Error::msg("not all arguments were consumed"))
+        }
+    }
+}
+
+/// Implemented for tuples of [`CandidType`] values, letting [`decode_args`]
+/// return a single tuple decoded from the message's whole argument list.
+pub trait ArgumentDecoder: Sized {
+    fn idl_deserialize(de: &mut IDLDeserialize<io::Cursor<Vec<u8>>>) -> Result<Self>;
+}
+
+macro_rules! tuple_decoder {
+    ($($t:ident),+) => {
+        impl<$($t: CandidType),+> ArgumentDecoder for ($($t,)+) {
+            fn idl_deserialize(de: &mut IDLDeserialize<io::Cursor<Vec<u8>>>) -> Result<Self> {
+                Ok(($(de.get_value::<$t>()?,)+))
+            }
+        }
+    };
+}
+
+tuple_decoder!(A);
+tuple_decoder!(A, B);
+tuple_decoder!(A, B, C);
+tuple_decoder!(A, B, C, D);
+
+/// Decode the arguments of a Candid message as a tuple of Rust types.
+pub fn decode_args<Tuple: ArgumentDecoder>(bytes: &[u8]) -> Result<Tuple> {
+    let mut de = IDLDeserialize::new(bytes)?;
+    let result = Tuple::idl_deserialize(&mut de)?;
+    de.done()?;
+    Ok(result)
+}
+
+/// Decode a single-argument Candid message.
+pub fn decode_one<T: CandidType>(bytes: &[u8]) -> Result<T> {
+    let mut de = IDLDeserialize::new(bytes)?;
+    let v = de.get_value::<T>()?;
+    de.done()?;
+    Ok(v)
+}
+
+/// A lightweight alternative to [`crate::ser::IDLBuilder`] /
+/// [`IDLDeserialize`] for framing a single self-contained Candid value --
+/// e.g. one message on a socket -- without the multi-argument builder
+/// boilerplate.
+pub trait Codec: CandidType + Sized {
+    /// Encode `self` as a complete one-argument Candid message.
+    fn encode<W: io::Write>(&self, w: &mut W) -> Result<()> {
+        w.write_all(b"DIDL")?;
+        let ty = Self::ty();
+        let (table, arg_refs) = TypeTable::build(std::slice::from_ref(&ty));
+        table.write(w, &arg_refs)?;
+        wire::write_value(w, &ty, &self.to_idl_value())
+    }
+
+    /// Decode a complete one-argument Candid message produced by [`encode`](Self::encode).
+    fn decode<R: io::Read>(r: &mut R) -> Result<Self> {
+        let mut de = IDLDeserialize::from_reader(r)?;
+        de.get_value::<Self>()
+    }
+}
+
+impl<T: CandidType> Codec for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::typing::TypeEnv;
+    use crate::parser::value::{IDLArgs, IDLValue};
+    use crate::types::internal::{Field, Label, Type};
+
+    #[test]
+    fn get_field_decodes_a_nested_field_and_skips_its_siblings() {
+        // A record with a field both before and after the one we target,
+        // nested under a variant and an opt, so decoding it exercises
+        // `extract_field`'s skip-trailing-siblings path as well as its
+        // recursive descent through `opt`/`variant`/`record`.
+        let inner_ty = Type::Record(vec![
+            Field { id: Label::Named("before".to_string()), ty: Type::Text, comments: Default::default() },
+            Field { id: Label::Named("target".to_string()), ty: Type::Nat32, comments: Default::default() },
+            Field { id: Label::Named("after".to_string()), ty: Type::Bool, comments: Default::default() },
+        ]);
+        let variant_ty = Type::Variant(vec![Field {
+            id: Label::Named("some".to_string()),
+            ty: inner_ty.clone(),
+            comments: Default::default(),
+        }]);
+        let ty = Type::Opt(Box::new(variant_ty.clone()));
+
+        let inner = IDLValue::Record(vec![
+            (Label::Named("before".to_string()), IDLValue::Text("skip me".to_string())),
+            (Label::Named("target".to_string()), IDLValue::Nat32(42)),
+            (Label::Named("after".to_string()), IDLValue::Bool(true)),
+        ]);
+        let value = IDLValue::Opt(Box::new(IDLValue::Variant(Box::new((
+            Label::Named("some".to_string()),
+            inner,
+        )))));
+
+        let args = IDLArgs { args: vec![IDLValue::Nat8(9), value] };
+        let env = TypeEnv::new();
+        let bytes = args
+            .to_bytes_with_types(&env, &[Type::Nat8, ty])
+            .unwrap();
+
+        let mut de = IDLDeserialize::new(&bytes).unwrap();
+        let target: u32 = de
+            .get_field(1, &["some", "target"])
+            .unwrap();
+        assert_eq!(target, 42);
+        de.done().unwrap();
+    }
+
+    #[test]
+    fn get_field_rejects_an_out_of_range_arg_index_instead_of_panicking() {
+        let args = IDLArgs { args: vec![IDLValue::Nat8(9)] };
+        let bytes = args.to_bytes().unwrap();
+        let mut de = IDLDeserialize::new(&bytes).unwrap();
+        assert!(de.get_field::<u32>(5, &[]).is_err());
+    }
+
+    #[test]
+    fn get_value_rejects_an_out_of_range_type_table_reference_instead_of_panicking() {
+        // An empty type table (0 entries) whose single argument ref points
+        // at table index 5 -- a malformed message, since a real encoder
+        // never emits a ref past the end of its own table.
+        let mut bytes = b"DIDL".to_vec();
+        bytes.extend([0, 1, 5]);
+        let mut de = IDLDeserialize::new(&bytes).unwrap();
+        assert!(de.get_value::<u32>().is_err());
+    }
+}
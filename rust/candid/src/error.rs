@@ -0,0 +1,56 @@
+//! Error handling for the Candid library.
+
+use std::fmt;
+
+/// A `Result` alias where the `Err` case is `candid::Error`.
+pub type Result<T = ()> = std::result::Result<T, Error>;
+
+/// Error type for the Candid library.
+#[derive(Debug)]
+pub enum Error {
+    /// A custom error message raised while parsing, encoding or decoding.
+    Custom(String),
+    /// An I/O error occurred while reading from or writing to a stream.
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Custom(msg) => write!(f, "{msg}"),
+            Error::Io(e) => write!(f, "io error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl Error {
+    /// Construct a custom error from anything that implements `Display`.
+    pub fn msg<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::error::Error::msg(format!($($arg)*))
+    };
+}
+
+/// Parse `str` into `T`, wrapping the underlying parse error with `name` for context.
+pub fn pretty_parse<T>(name: &str, str: &str) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: fmt::Display,
+{
+    str.parse::<T>()
+        .map_err(|e| Error::msg(format!("Parsing {name} error: {e}")))
+}
@@ -0,0 +1,105 @@
+//! LEB128 varint encoding, used throughout the Candid wire format for type
+//! table indices, field counts and the `nat`/`int` value encoding.
+
+use crate::error::{Error, Result};
+use std::io::{Read, Write};
+
+pub fn write_unsigned<W: Write>(w: &mut W, mut value: u64) -> Result<()> {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        w.write_all(&[byte])?;
+        if value == 0 {
+            return Ok(());
+        }
+    }
+}
+
+pub fn read_unsigned<R: Read>(r: &mut R) -> Result<u64> {
+    let mut result: u64 = 0;
+    let mut shift = 0;
+    loop {
+        if shift >= 64 {
+            return Err(Error::msg("LEB128 value too large for a 64-bit integer"));
+        }
+        let mut byte = [0u8; 1];
+        r.read_exact(&mut byte)?;
+        result |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+    }
+}
+
+pub fn write_signed<W: Write>(w: &mut W, mut value: i64) -> Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        w.write_all(&[if done { byte } else { byte | 0x80 }])?;
+        if done {
+            return Ok(());
+        }
+    }
+}
+
+pub fn read_signed<R: Read>(r: &mut R) -> Result<i64> {
+    let mut result: i64 = 0;
+    let mut shift = 0;
+    let mut byte;
+    loop {
+        if shift >= 64 {
+            return Err(Error::msg("LEB128 value too large for a 64-bit integer"));
+        }
+        let mut buf = [0u8; 1];
+        r.read_exact(&mut buf)?;
+        byte = buf[0];
+        result |= ((byte & 0x7f) as i64) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    if shift < 64 && byte & 0x40 != 0 {
+        result |= -1i64 << shift;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn read_unsigned_rejects_a_run_of_continuation_bytes_instead_of_panicking() {
+        let bytes = [0xffu8; 11];
+        assert!(read_unsigned(&mut Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn read_signed_rejects_a_run_of_continuation_bytes_instead_of_panicking() {
+        let bytes = [0xffu8; 11];
+        assert!(read_signed(&mut Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn read_unsigned_round_trips_a_large_value() {
+        let mut buf = Vec::new();
+        write_unsigned(&mut buf, u64::MAX).unwrap();
+        assert_eq!(read_unsigned(&mut Cursor::new(buf)).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn read_signed_round_trips_negative_and_positive_values() {
+        for value in [i64::MIN, i64::MAX, -1, 0, 42] {
+            let mut buf = Vec::new();
+            write_signed(&mut buf, value).unwrap();
+            assert_eq!(read_signed(&mut Cursor::new(buf)).unwrap(), value);
+        }
+    }
+}
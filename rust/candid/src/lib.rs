@@ -259,6 +259,9 @@ pub use parser::types::IDLProg;
 pub use parser::typing::{check_prog, TypeEnv};
 pub use parser::value::IDLArgs;
 
+mod leb128;
+mod wire;
+
 pub mod de;
 pub use de::{decode_args, decode_one};
 pub mod ser;
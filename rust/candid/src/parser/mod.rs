@@ -0,0 +1,5 @@
+//! Parsing and untyped representations of Candid values and `.did` files.
+
+pub mod typing;
+pub mod types;
+pub mod value;
@@ -0,0 +1,647 @@
+//! The AST for a parsed `.did` file, and a pretty printer that renders an
+//! [`IDLProg`] back into canonical Candid syntax.
+//!
+//! Comments are part of this AST: [`split_chunks_with_comments`] pulls `//`
+//! and `/* .. */` comments out of the source as it splits it into
+//! declarations, attaching whole comment lines written directly above a
+//! declaration as its [`Comments::leading`] and a same-line comment after it
+//! as its [`Comments::trailing`]. The same mechanism attaches comments to
+//! record/variant fields (see [`parse_fields`]) and to each argument and
+//! return type of a method signature (see [`parse_type_list`],
+//! [`Function::arg_comments`](crate::types::internal::Function::arg_comments)).
+//! [`to_pretty`] re-emits them, so `to_pretty(&s.parse()?, width).parse::<IDLProg>()`
+//! round-trips both the types and their comments. A comment that isn't
+//! directly above or after a declaration isn't attached to anything and is
+//! dropped: this includes a trailing comment at the very end of the file
+//! with no declaration after it, same as the rest of this parser's
+//! simplifications.
+
+use crate::error::{Error, Result};
+use crate::parser::value::IDLArgs;
+use crate::types::internal::{Comments, Function, Label, Type};
+use std::str::FromStr;
+
+/// A `type Name = T;` declaration, together with the comments written
+/// around it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeDec {
+    pub name: String,
+    pub ty: Type,
+    pub comments: Comments,
+}
+
+/// A top-level declaration in a `.did` file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Dec {
+    Type(TypeDec),
+}
+
+/// The parsed form of a `.did` file: its type declarations and, optionally,
+/// the service (actor) type it describes.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct IDLProg {
+    pub decs: Vec<Dec>,
+    pub actor: Option<Type>,
+    /// Comments attached to each method in `actor`'s service type, keyed by
+    /// method name (methods names are unique within a service).
+    pub method_comments: Vec<(String, Comments)>,
+}
+
+impl IDLProg {
+    /// The comments attached to the service method named `name`, if any.
+    pub fn comments_for_method(&self, name: &str) -> Option<&Comments> {
+        self.method_comments
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, c)| c)
+    }
+}
+
+impl FromStr for IDLProg {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Parser::new(s).parse_prog()
+    }
+}
+
+/// Pretty-print a parsed `.did` file back into canonical Candid syntax,
+/// re-emitting the comments attached to its declarations. Each declaration
+/// and method is printed on a single line; `width` does not wrap long
+/// lines. A trailing comment on the last argument or return type of a
+/// method is not printed (see [`render_type_list`]).
+pub fn to_pretty(prog: &IDLProg, width: usize) -> String {
+    let mut out = String::new();
+    for dec in &prog.decs {
+        let Dec::Type(dec) = dec;
+        render_comments(&mut out, &dec.comments, "");
+        let line = format!("type {} = {};", dec.name, render_type(&dec.ty, width));
+        render_trailing(&mut out, &line, &dec.comments);
+    }
+    if let Some(Type::Service(methods)) = &prog.actor {
+        out.push_str("service : {\n");
+        for (name, func) in methods {
+            let comments = prog.comments_for_method(name).cloned().unwrap_or_default();
+            render_comments(&mut out, &comments, "  ");
+            let args = render_type_list(&func.args, &func.arg_comments, width);
+            let rets = render_type_list(&func.rets, &func.ret_comments, width);
+            let query = if func.is_query { " query" } else { "" };
+            let line = format!("  {name} : ({args}) -> ({rets}){query};");
+            render_trailing(&mut out, &line, &comments);
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+fn render_comments(out: &mut String, comments: &Comments, indent: &str) {
+    for entry in &comments.leading {
+        // A leading comment's text can itself contain a newline if it came
+        // from a multi-line `/* .. */` block; split it so every physical
+        // line still gets its own `//` marker.
+        for line in entry.split('\n') {
+            out.push_str(indent);
+            out.push_str("//");
+            if !line.is_empty() {
+                out.push(' ');
+                out.push_str(line);
+            }
+            out.push('\n');
+        }
+    }
+}
+
+fn render_trailing(out: &mut String, line: &str, comments: &Comments) {
+    out.push_str(line);
+    if let Some(trailing) = &comments.trailing {
+        // Rendered as a block comment (regardless of how it was originally
+        // written) so it stays on this line even if its text spans
+        // multiple physical lines.
+        out.push_str(" /*");
+        out.push_str(&escape_comment(trailing));
+        out.push_str("*/");
+    }
+    out.push('\n');
+}
+
+/// Escape any `*/` inside `text` so it can be wrapped in a `/* .. */` block
+/// comment without closing it early.
+fn escape_comment(text: &str) -> String {
+    text.replace("*/", "* /")
+}
+
+fn render_type(ty: &Type, width: usize) -> String {
+    match ty {
+        Type::Null => "null".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::Nat => "nat".to_string(),
+        Type::Int => "int".to_string(),
+        Type::Nat8 => "nat8".to_string(),
+        Type::Nat16 => "nat16".to_string(),
+        Type::Nat32 => "nat32".to_string(),
+        Type::Nat64 => "nat64".to_string(),
+        Type::Int8 => "int8".to_string(),
+        Type::Int16 => "int16".to_string(),
+        Type::Int32 => "int32".to_string(),
+        Type::Int64 => "int64".to_string(),
+        Type::Float32 => "float32".to_string(),
+        Type::Float64 => "float64".to_string(),
+        Type::Text => "text".to_string(),
+        Type::Reserved => "reserved".to_string(),
+        Type::Empty => "empty".to_string(),
+        Type::Principal => "principal".to_string(),
+        Type::Var(name) => name.clone(),
+        Type::Opt(t) => format!("opt {}", render_type(t, width)),
+        Type::Vec(t) => format!("vec {}", render_type(t, width)),
+        Type::Record(fs) => format!("record {{ {} }}", render_fields(fs, width)),
+        Type::Variant(fs) => format!("variant {{ {} }}", render_fields(fs, width)),
+        Type::Service(_) => "service".to_string(),
+        Type::Func(_) => "func".to_string(),
+        Type::Knot(t) => render_type(t, width),
+    }
+}
+
+/// Render a method's argument or return-type list, with each entry's
+/// leading comment as a `/* .. */` block right before its type, and a
+/// trailing comment right after the `,` that follows it -- the same
+/// position a trailing comment ends up in after the `;` of a record field
+/// (see [`render_fields`]), since a comma list has no per-element
+/// terminator of its own to hang one off of. Unlike a record's last field,
+/// which still gets its own `;`, the last element of the list can't carry
+/// a trailing comment: there's no separator left to place it after.
+fn render_type_list(tys: &[Type], comments: &[Comments], width: usize) -> String {
+    // `comments` is expected to be the same length as `tys` (every
+    // `Function` built by `parse_function` keeps them in sync), but since
+    // both are public fields with no constructor, fall back to no comments
+    // for an entry rather than panicking if a caller built one by hand and
+    // let them drift apart.
+    let empty = Comments::default();
+    let comment_at = |i: usize| comments.get(i).unwrap_or(&empty);
+    let mut out = String::new();
+    for (i, ty) in tys.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+            if let Some(trailing) = &comment_at(i - 1).trailing {
+                out.push_str(&format!(" /*{}*/", escape_comment(trailing)));
+            }
+            out.push(' ');
+        }
+        for line in &comment_at(i).leading {
+            out.push_str("/*");
+            out.push_str(&escape_comment(line));
+            out.push_str("*/ ");
+        }
+        out.push_str(&render_type(ty, width));
+    }
+    out
+}
+
+fn render_fields(fs: &[crate::types::internal::Field], width: usize) -> String {
+    // Every field gets its own `;`, with a trailing comment placed after
+    // it: that keeps the comment out of the field's own declaration, so
+    // re-parsing attaches it back as this field's trailing comment instead
+    // of the next field's leading one.
+    let mut out = String::new();
+    for (i, f) in fs.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        for line in &f.comments.leading {
+            out.push_str("/*");
+            out.push_str(&escape_comment(line));
+            out.push_str("*/ ");
+        }
+        out.push_str(&format!("{}: {};", f.id, render_type(&f.ty, width)));
+        if let Some(trailing) = &f.comments.trailing {
+            out.push_str(&format!(" /*{}*/", escape_comment(trailing)));
+        }
+    }
+    out
+}
+
+/// Parse a sequence of Candid values in text format, e.g.
+/// `(42, opt true, vec {1;2;3})`.
+pub(crate) fn parse_idl_args(s: &str) -> Result<IDLArgs> {
+    Parser::new(s).parse_args()
+}
+
+/// A minimal hand-rolled recursive-descent parser for `.did` files
+/// (`parse_prog`, split with [`split_chunks_with_comments`]) and Candid
+/// value literals (`parse_args`, split with [`split_top_level`]).
+struct Parser<'a> {
+    input: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser { input }
+    }
+
+    fn parse_prog(&self) -> Result<IDLProg> {
+        // A compact subset of the grammar: `type Name = T;` declarations
+        // followed by an optional `service : { ... }` block.
+        let mut decs = Vec::new();
+        let mut actor = None;
+        let mut method_comments = Vec::new();
+        for chunk in split_chunks_with_comments(self.input, ';') {
+            let code = chunk.code.trim();
+            if code.is_empty() {
+                continue;
+            }
+            if let Some(rest) = code.strip_prefix("type ") {
+                let (name, ty_str) = rest
+                    .split_once('=')
+                    .ok_or_else(|| Error::msg("expected `=` in type declaration"))?;
+                decs.push(Dec::Type(TypeDec {
+                    name: name.trim().to_string(),
+                    ty: parse_type(ty_str.trim())?,
+                    comments: chunk.comments,
+                }));
+            } else if let Some(rest) = code.strip_prefix("service") {
+                let rest = rest.trim().trim_start_matches(':').trim();
+                let body = rest
+                    .strip_prefix('{')
+                    .and_then(|s| s.strip_suffix('}'))
+                    .ok_or_else(|| Error::msg("expected `{ .. }` in service declaration"))?;
+                let mut methods = Vec::new();
+                for m in split_chunks_with_comments(body, ';') {
+                    let code = m.code.trim();
+                    if code.is_empty() {
+                        continue;
+                    }
+                    let (name, sig) = code
+                        .split_once(':')
+                        .ok_or_else(|| Error::msg("expected `:` in method signature"))?;
+                    let name = name.trim().to_string();
+                    methods.push((name.clone(), parse_function(sig.trim())?));
+                    method_comments.push((name, m.comments));
+                }
+                actor = Some(Type::Service(methods));
+            }
+        }
+        Ok(IDLProg {
+            decs,
+            actor,
+            method_comments,
+        })
+    }
+
+    fn parse_args(&self) -> Result<IDLArgs> {
+        let s = self.input.trim();
+        let s = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or_else(|| Error::msg("expected a parenthesized argument list"))?;
+        let mut args = Vec::new();
+        for part in split_top_level(s, ',') {
+            let part = part.trim();
+            if !part.is_empty() {
+                args.push(crate::parser::value::parse_idl_value(part)?);
+            }
+        }
+        Ok(IDLArgs { args })
+    }
+}
+
+fn parse_function(sig: &str) -> Result<Function> {
+    let is_query = sig.trim_end().ends_with("query");
+    let sig = sig.trim_end().trim_end_matches("query").trim_end();
+    let (params, rest) = sig
+        .split_once("->")
+        .ok_or_else(|| Error::msg("expected `->` in method signature"))?;
+    let (args, arg_comments) = parse_type_list(params.trim())?;
+    let (rets, ret_comments) = parse_type_list(rest.trim())?;
+    Ok(Function {
+        args,
+        rets,
+        is_query,
+        arg_comments,
+        ret_comments,
+    })
+}
+
+/// Parse a parenthesized, comma-separated list of argument/return types,
+/// e.g. the `(a, b)` of `(a, b) -> (c)`, together with each entry's
+/// leading/trailing comments (see the module docs), the same way
+/// [`parse_fields`] does for record/variant fields.
+fn parse_type_list(s: &str) -> Result<(Vec<Type>, Vec<Comments>)> {
+    let s = s
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .ok_or_else(|| Error::msg("expected a parenthesized type list"))?;
+    split_chunks_with_comments(s, ',')
+        .into_iter()
+        .filter(|chunk| !chunk.code.trim().is_empty())
+        .map(|chunk| Ok((parse_type(chunk.code.trim())?, chunk.comments)))
+        .collect()
+}
+
+pub(crate) fn parse_type(s: &str) -> Result<Type> {
+    let s = s.trim();
+    if let Some(rest) = s.strip_prefix("opt ") {
+        return Ok(Type::Opt(Box::new(parse_type(rest)?)));
+    }
+    if let Some(rest) = s.strip_prefix("vec ") {
+        return Ok(Type::Vec(Box::new(parse_type(rest)?)));
+    }
+    if let Some(body) = strip_keyword(s, "record") {
+        return parse_fields(body.trim_start()).map(Type::Record);
+    }
+    if let Some(body) = strip_keyword(s, "variant") {
+        return parse_fields(body.trim_start()).map(Type::Variant);
+    }
+    Ok(match s {
+        "null" => Type::Null,
+        "bool" => Type::Bool,
+        "nat" => Type::Nat,
+        "int" => Type::Int,
+        "nat8" => Type::Nat8,
+        "nat16" => Type::Nat16,
+        "nat32" => Type::Nat32,
+        "nat64" => Type::Nat64,
+        "int8" => Type::Int8,
+        "int16" => Type::Int16,
+        "int32" => Type::Int32,
+        "int64" => Type::Int64,
+        "float32" => Type::Float32,
+        "float64" => Type::Float64,
+        "text" => Type::Text,
+        "reserved" => Type::Reserved,
+        "empty" => Type::Empty,
+        "principal" => Type::Principal,
+        name => Type::Var(name.to_string()),
+    })
+}
+
+/// Strip `kw` as a leading keyword from `s`, requiring it to end at a word
+/// boundary (whitespace, `{`, or end of input) so a type name that merely
+/// starts with `kw` (e.g. `recorder`, `variantKind`) isn't mistaken for it.
+fn strip_keyword<'a>(s: &'a str, kw: &str) -> Option<&'a str> {
+    let rest = s.strip_prefix(kw)?;
+    match rest.chars().next() {
+        None | Some('{') => Some(rest),
+        Some(c) if c.is_whitespace() => Some(rest),
+        _ => None,
+    }
+}
+
+fn parse_fields(body: &str) -> Result<Vec<crate::types::internal::Field>> {
+    let body = body
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .ok_or_else(|| Error::msg("expected `{ .. }` field list"))?;
+    split_chunks_with_comments(body, ';')
+        .into_iter()
+        .filter(|chunk| !chunk.code.trim().is_empty())
+        .map(|chunk| {
+            let f = chunk.code.trim();
+            let (label, ty) = f
+                .split_once(':')
+                .ok_or_else(|| Error::msg("expected `label: type`"))?;
+            let label = label.trim();
+            let id = label
+                .parse::<u32>()
+                .map(Label::Unnamed)
+                .unwrap_or_else(|_| Label::Named(label.to_string()));
+            Ok(crate::types::internal::Field {
+                id,
+                ty: parse_type(ty.trim())?,
+                comments: chunk.comments,
+            })
+        })
+        .collect()
+}
+
+/// Split `s` on `sep`, ignoring occurrences nested inside `(`, `{`, `[` or
+/// string literals.
+fn split_top_level(s: &str, sep: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut in_str = false;
+    let mut cur = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => {
+                in_str = !in_str;
+                cur.push(c);
+            }
+            '(' | '{' | '[' if !in_str => {
+                depth += 1;
+                cur.push(c);
+            }
+            ')' | '}' | ']' if !in_str => {
+                depth -= 1;
+                cur.push(c);
+            }
+            c if c == sep && depth == 0 && !in_str => {
+                parts.push(std::mem::take(&mut cur));
+            }
+            c => cur.push(c),
+        }
+    }
+    if !cur.trim().is_empty() {
+        parts.push(cur);
+    }
+    parts
+}
+
+/// One top-level `sep`-terminated chunk of `.did` source, together with the
+/// comments written immediately around it. See the module docs for what
+/// counts as "immediately around".
+struct Chunk {
+    code: String,
+    comments: Comments,
+}
+
+/// Like [`split_top_level`], but also pulls `//` and `/* .. */` comments out
+/// of the source: whole comment lines seen between two declarations become
+/// the next declaration's [`Comments::leading`], and a comment seen right
+/// after a declaration's `sep` on the same line becomes its
+/// [`Comments::trailing`]. Comments nested inside a chunk (e.g. inside a
+/// `record { .. }`) are left untouched in that chunk's `code`, so a later
+/// recursive call over that nested body (see [`parse_fields`]) can attach
+/// them at the right level.
+fn split_chunks_with_comments(s: &str, sep: char) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut depth = 0i32;
+    let mut in_str = false;
+    let mut code = String::new();
+    let mut code_started = false;
+    let mut pending_leading: Vec<String> = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_str = !in_str;
+                code.push(c);
+                code_started = true;
+            }
+            '/' if depth == 0 && !in_str && chars.peek() == Some(&'/') => {
+                chars.next();
+                pending_leading.push(take_line_comment(&mut chars));
+            }
+            '/' if depth == 0 && !in_str && chars.peek() == Some(&'*') => {
+                chars.next();
+                pending_leading.push(take_block_comment(&mut chars));
+            }
+            '(' | '{' | '[' if !in_str => {
+                depth += 1;
+                code.push(c);
+                code_started = true;
+            }
+            ')' | '}' | ']' if !in_str => {
+                depth -= 1;
+                code.push(c);
+                code_started = true;
+            }
+            c if c == sep && depth == 0 && !in_str => {
+                let leading = std::mem::take(&mut pending_leading);
+                let trailing = peek_trailing_comment(&mut chars);
+                chunks.push(Chunk {
+                    // Newlines are kept (not flattened to spaces) so that a
+                    // nested body re-split by a recursive call (see
+                    // `parse_fields`, the `service` branch of `parse_prog`)
+                    // still has line boundaries to attach its own comments
+                    // against.
+                    code: std::mem::take(&mut code),
+                    comments: Comments { leading, trailing },
+                });
+                code_started = false;
+            }
+            c => {
+                if !c.is_whitespace() {
+                    code_started = true;
+                }
+                code.push(c);
+            }
+        }
+    }
+    if code_started {
+        chunks.push(Chunk {
+            code,
+            comments: Comments {
+                leading: pending_leading,
+                trailing: None,
+            },
+        });
+    }
+    chunks
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn take_line_comment(chars: &mut Chars<'_>) -> String {
+    let mut text = String::new();
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            break;
+        }
+        text.push(c);
+        chars.next();
+    }
+    text.trim().to_string()
+}
+
+fn take_block_comment(chars: &mut Chars<'_>) -> String {
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        if c == '*' && chars.peek() == Some(&'/') {
+            chars.next();
+            break;
+        }
+        text.push(c);
+    }
+    text.trim().to_string()
+}
+
+/// If a `//` or `/* .. */` comment follows on the same line (only spaces
+/// and tabs in between), consume and return it.
+fn peek_trailing_comment(chars: &mut Chars<'_>) -> Option<String> {
+    let mut lookahead = chars.clone();
+    while matches!(lookahead.peek(), Some(' ') | Some('\t')) {
+        lookahead.next();
+    }
+    if lookahead.peek() != Some(&'/') {
+        return None;
+    }
+    let mut probe = lookahead.clone();
+    probe.next();
+    let comment = match probe.peek() {
+        Some('/') => {
+            probe.next();
+            Some(take_line_comment(&mut probe))
+        }
+        Some('*') => {
+            probe.next();
+            Some(take_block_comment(&mut probe))
+        }
+        _ => None,
+    };
+    if comment.is_some() {
+        *chars = probe;
+    }
+    comment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_prints_and_reparses_record_variant_and_opt() {
+        let src = "type t = record { tag: variant { ok: bool; err: text }; note: opt nat8 };\n";
+        let prog: IDLProg = src.parse().unwrap();
+        let pretty = to_pretty(&prog, 80);
+        let reparsed: IDLProg = pretty.parse().unwrap();
+        assert_eq!(prog, reparsed);
+    }
+
+    #[test]
+    fn round_trips_leading_and_trailing_comments() {
+        let src = "// a leading comment\ntype t = nat; /* a trailing comment */\n";
+        let prog: IDLProg = src.parse().unwrap();
+        let pretty = to_pretty(&prog, 80);
+        let reparsed: IDLProg = pretty.parse().unwrap();
+        assert_eq!(prog, reparsed);
+    }
+
+    #[test]
+    fn type_names_starting_with_record_or_variant_are_not_mistaken_for_keywords() {
+        assert_eq!(parse_type("recorder").unwrap(), Type::Var("recorder".to_string()));
+        assert_eq!(parse_type("variantKind").unwrap(), Type::Var("variantKind".to_string()));
+    }
+
+    #[test]
+    fn parse_type_list_parses_each_element() {
+        let (tys, _) = parse_type_list("(nat, text, opt bool)").unwrap();
+        assert_eq!(tys, vec![Type::Nat, Type::Text, Type::Opt(Box::new(Type::Bool))]);
+    }
+
+    #[test]
+    fn parse_type_list_attaches_comments_to_each_argument() {
+        // A comma list has no per-element terminator, so (like the record
+        // field convention) a trailing comment has to sit right after the
+        // separator that follows its element, not before it.
+        let (tys, comments) =
+            parse_type_list("(/* the id */ nat, text, /* a name */ opt bool)").unwrap();
+        assert_eq!(tys, vec![Type::Nat, Type::Text, Type::Opt(Box::new(Type::Bool))]);
+        assert_eq!(comments[0].leading, vec!["the id".to_string()]);
+        assert_eq!(comments[1].trailing, Some("a name".to_string()));
+        assert!(comments[2].is_empty());
+    }
+
+    #[test]
+    fn round_trips_comments_on_method_arguments_and_return_types() {
+        let src = "service : { f : (/* the id */ nat, bool) -> (text, /* a count */ nat32); }\n";
+        let prog: IDLProg = src.parse().unwrap();
+        let pretty = to_pretty(&prog, 80);
+        let reparsed: IDLProg = pretty.parse().unwrap();
+        assert_eq!(prog, reparsed);
+        let Some(Type::Service(methods)) = &prog.actor else {
+            panic!("expected a service type");
+        };
+        let (_, func) = &methods[0];
+        assert_eq!(func.arg_comments[0].leading, vec!["the id".to_string()]);
+        assert_eq!(func.ret_comments[0].trailing, Some("a count".to_string()));
+    }
+}
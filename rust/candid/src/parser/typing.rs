@@ -0,0 +1,67 @@
+//! Type checking for parsed `.did` files.
+
+use crate::error::{Error, Result};
+use crate::parser::types::{Dec, IDLProg};
+use crate::types::internal::{Function, Type};
+use std::collections::HashMap;
+
+/// Maps the type names declared in a `.did` file to their resolved
+/// [`Type`], and the service's methods to their [`Function`] signature.
+#[derive(Clone, Debug, Default)]
+pub struct TypeEnv {
+    types: HashMap<String, Type>,
+    methods: HashMap<String, Function>,
+}
+
+impl TypeEnv {
+    pub fn new() -> Self {
+        TypeEnv::default()
+    }
+
+    pub fn find_type(&self, name: &str) -> Result<&Type> {
+        self.types
+            .get(name)
+            .ok_or_else(|| Error::msg(format!("unbound type identifier {name}")))
+    }
+
+    /// Look up a method declared on `actor`'s service type by name.
+    pub fn get_method(&self, _actor: &Type, name: &str) -> Result<&Function> {
+        self.methods
+            .get(name)
+            .ok_or_else(|| Error::msg(format!("method {name} not found")))
+    }
+
+    /// Resolve `ty` one level through this environment if it's a
+    /// [`Type::Var`], leaving every other type unchanged.
+    pub fn trace_type(&self, ty: &Type) -> Result<Type> {
+        match ty {
+            Type::Var(name) => self.find_type(name).cloned(),
+            other => Ok(other.clone()),
+        }
+    }
+}
+
+impl Function {
+    pub fn is_query(&self) -> bool {
+        self.is_query
+    }
+}
+
+/// Type-check a parsed `.did` file, populating `env` with its declarations
+/// and returning the actor (service) type, if one was declared.
+pub fn check_prog(env: &mut TypeEnv, prog: &IDLProg) -> Result<Option<Type>> {
+    for dec in &prog.decs {
+        let Dec::Type(dec) = dec;
+        env.types.insert(dec.name.clone(), dec.ty.clone());
+    }
+    if let Some(actor) = &prog.actor {
+        if let Type::Service(methods) = actor {
+            for (name, func) in methods {
+                env.methods.insert(name.clone(), func.clone());
+            }
+        }
+        Ok(Some(actor.clone()))
+    } else {
+        Ok(None)
+    }
+}
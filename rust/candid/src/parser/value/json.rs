@@ -0,0 +1,356 @@
+//! Lossless conversion between [`IDLValue`]/[`IDLArgs`] and JSON.
+//!
+//! The untyped methods ([`IDLValue::to_json`], [`IDLValue::from_json`]) infer
+//! everything from the value's own shape, the same way [`IDLArgs::to_bytes`]
+//! infers a `Type` from an [`IDLValue`]. That inference is lossy in the
+//! string direction: `nat`, `int` and every fixed-width integer are all
+//! rendered as JSON strings (so they survive a JSON round-trip without
+//! precision loss), which means decoding a bare string back can't tell a
+//! `nat` from a `text` that happens to look like one, and a record with a
+//! single field is indistinguishable from a variant. The typed methods
+//! ([`IDLValue::to_json_with_type`], [`IDLValue::from_json_with_type`], and
+//! their `IDLArgs` equivalents) resolve both ambiguities using a [`Type`]
+//! looked up in a [`TypeEnv`]: it fixes the number width a string decodes
+//! to, and recovers field names that are otherwise only known by hash.
+
+use super::{hex, IDLArgs, IDLValue};
+use crate::error::{Error, Result};
+use crate::parser::typing::TypeEnv;
+use crate::types::internal::{Label, Type};
+use crate::types::number::{Int, Nat};
+use crate::types::principal::Principal;
+use serde_json::{Map, Value};
+
+fn decode_hex(s: &str) -> Result<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if !s.len().is_multiple_of(2) {
+        return Err(Error::msg("hex string has an odd number of digits"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&s[i..i + 2], 16)
+                .map_err(|e| Error::msg(format!("invalid hex string: {e}")))
+        })
+        .collect()
+}
+
+fn expect_str<'a>(v: &'a Value, what: &str) -> Result<&'a str> {
+    v.as_str()
+        .ok_or_else(|| Error::msg(format!("expected a JSON string for {what}, got {v}")))
+}
+
+impl IDLValue {
+    /// Convert to JSON, inferring the mapping from `self`'s own shape. See
+    /// the module docs for the lossiness this implies on decode.
+    pub fn to_json(&self) -> Value {
+        match self {
+            IDLValue::Bool(b) => Value::Bool(*b),
+            IDLValue::Null | IDLValue::None => Value::Null,
+            IDLValue::Text(s) => Value::String(s.clone()),
+            IDLValue::Principal(p) => Value::String(p.to_string()),
+            IDLValue::Nat(n) => Value::String(n.to_string()),
+            IDLValue::Int(n) => Value::String(n.to_string()),
+            IDLValue::Nat8(n) => Value::String(n.to_string()),
+            IDLValue::Nat16(n) => Value::String(n.to_string()),
+            IDLValue::Nat32(n) => Value::String(n.to_string()),
+            IDLValue::Nat64(n) => Value::String(n.to_string()),
+            IDLValue::Int8(n) => Value::String(n.to_string()),
+            IDLValue::Int16(n) => Value::String(n.to_string()),
+            IDLValue::Int32(n) => Value::String(n.to_string()),
+            IDLValue::Int64(n) => Value::String(n.to_string()),
+            IDLValue::Float32(n) => serde_json::json!(*n as f64),
+            IDLValue::Float64(n) => serde_json::json!(n),
+            IDLValue::Blob(b) => Value::String(format!("0x{}", hex(b))),
+            IDLValue::Opt(v) => v.to_json(),
+            IDLValue::Vec(vs) => Value::Array(vs.iter().map(IDLValue::to_json).collect()),
+            IDLValue::Record(fs) => {
+                let mut obj = Map::new();
+                for (label, v) in fs {
+                    obj.insert(label.to_string(), v.to_json());
+                }
+                Value::Object(obj)
+            }
+            IDLValue::Variant(v) => {
+                let mut obj = Map::new();
+                obj.insert(v.0.to_string(), v.1.to_json());
+                Value::Object(obj)
+            }
+        }
+    }
+
+    /// Convert from JSON, guessing a shape for values whose `Type` isn't
+    /// known: numbers become [`IDLValue::Float64`], strings are parsed as an
+    /// [`IDLValue::Int`] when possible and fall back to [`IDLValue::Text`]
+    /// otherwise, and objects always become [`IDLValue::Record`] (never
+    /// [`IDLValue::Variant`] -- see the module docs). Prefer
+    /// [`IDLValue::from_json_with_type`] when a `Type` is available.
+    pub fn from_json(v: &Value) -> Result<Self> {
+        match v {
+            Value::Null => Ok(IDLValue::Null),
+            Value::Bool(b) => Ok(IDLValue::Bool(*b)),
+            Value::Number(n) => n
+                .as_f64()
+                .map(IDLValue::Float64)
+                .ok_or_else(|| Error::msg(format!("number out of range: {n}"))),
+            Value::String(s) => Ok(s
+                .parse::<Int>()
+                .map(IDLValue::Int)
+                .unwrap_or_else(|_| IDLValue::Text(s.clone()))),
+            Value::Array(vs) => Ok(IDLValue::Vec(
+                vs.iter().map(IDLValue::from_json).collect::<Result<_>>()?,
+            )),
+            Value::Object(obj) => Ok(IDLValue::Record(
+                obj.iter()
+                    .map(|(k, v)| Ok((parse_label(k), IDLValue::from_json(v)?)))
+                    .collect::<Result<_>>()?,
+            )),
+        }
+    }
+
+    /// Convert to JSON using `ty` to recover field names that [`Label`]
+    /// alone only knows as a hash.
+    pub fn to_json_with_type(&self, env: &TypeEnv, ty: &Type) -> Result<Value> {
+        let ty = env.trace_type(ty)?;
+        match (self, &ty) {
+            (IDLValue::Opt(v), Type::Opt(inner)) => v.to_json_with_type(env, inner),
+            (IDLValue::Vec(vs), Type::Vec(inner)) => Ok(Value::Array(
+                vs.iter()
+                    .map(|v| v.to_json_with_type(env, inner))
+                    .collect::<Result<_>>()?,
+            )),
+            (IDLValue::Record(fs), Type::Record(_)) => {
+                let mut obj = Map::new();
+                for (label, v) in fs {
+                    let field = ty.field_by_hash(label.get_id());
+                    let key = field.map(|f| f.id.to_string()).unwrap_or_else(|| label.to_string());
+                    let value = match field {
+                        Some(f) => v.to_json_with_type(env, &f.ty)?,
+                        None => v.to_json(),
+                    };
+                    obj.insert(key, value);
+                }
+                Ok(Value::Object(obj))
+            }
+            (IDLValue::Variant(v), Type::Variant(_)) => {
+                let (label, inner) = v.as_ref();
+                let field = ty.field_by_hash(label.get_id());
+                let key = field.map(|f| f.id.to_string()).unwrap_or_else(|| label.to_string());
+                let value = match field {
+                    Some(f) => inner.to_json_with_type(env, &f.ty)?,
+                    None => inner.to_json(),
+                };
+                let mut obj = Map::new();
+                obj.insert(key, value);
+                Ok(Value::Object(obj))
+            }
+            (v, _) => Ok(v.to_json()),
+        }
+    }
+
+    /// Convert from JSON using `ty` to disambiguate number widths and
+    /// recover field names from hashes, the inverse of
+    /// [`IDLValue::to_json_with_type`].
+    pub fn from_json_with_type(v: &Value, env: &TypeEnv, ty: &Type) -> Result<Self> {
+        let ty = env.trace_type(ty)?;
+        match &ty {
+            Type::Null | Type::Reserved | Type::Empty => Ok(IDLValue::Null),
+            Type::Bool => v
+                .as_bool()
+                .map(IDLValue::Bool)
+                .ok_or_else(|| Error::msg(format!("expected a JSON bool, got {v}"))),
+            Type::Text => Ok(IDLValue::Text(expect_str(v, "text")?.to_string())),
+            Type::Principal => Ok(IDLValue::Principal(Principal::from_slice(&decode_hex(
+                expect_str(v, "principal")?,
+            )?))),
+            Type::Nat => Ok(IDLValue::Nat(expect_str(v, "nat")?.parse::<Nat>()?)),
+            Type::Int => Ok(IDLValue::Int(expect_str(v, "int")?.parse::<Int>()?)),
+            Type::Nat8 => parse_num(v, "nat8").map(IDLValue::Nat8),
+            Type::Nat16 => parse_num(v, "nat16").map(IDLValue::Nat16),
+            Type::Nat32 => parse_num(v, "nat32").map(IDLValue::Nat32),
+            Type::Nat64 => parse_num(v, "nat64").map(IDLValue::Nat64),
+            Type::Int8 => parse_num(v, "int8").map(IDLValue::Int8),
+            Type::Int16 => parse_num(v, "int16").map(IDLValue::Int16),
+            Type::Int32 => parse_num(v, "int32").map(IDLValue::Int32),
+            Type::Int64 => parse_num(v, "int64").map(IDLValue::Int64),
+            Type::Float32 => v
+                .as_f64()
+                .map(|f| IDLValue::Float32(f as f32))
+                .ok_or_else(|| Error::msg(format!("expected a JSON number, got {v}"))),
+            Type::Float64 => v
+                .as_f64()
+                .map(IDLValue::Float64)
+                .ok_or_else(|| Error::msg(format!("expected a JSON number, got {v}"))),
+            Type::Vec(inner) if **inner == Type::Nat8 => {
+                Ok(IDLValue::Blob(decode_hex(expect_str(v, "blob")?)?))
+            }
+            Type::Opt(inner) => {
+                if v.is_null() {
+                    Ok(IDLValue::None)
+                } else {
+                    Ok(IDLValue::Opt(Box::new(IDLValue::from_json_with_type(
+                        v, env, inner,
+                    )?)))
+                }
+            }
+            Type::Vec(inner) => {
+                let items = v
+                    .as_array()
+                    .ok_or_else(|| Error::msg(format!("expected a JSON array, got {v}")))?;
+                Ok(IDLValue::Vec(
+                    items
+                        .iter()
+                        .map(|v| IDLValue::from_json_with_type(v, env, inner))
+                        .collect::<Result<_>>()?,
+                ))
+            }
+            Type::Record(fields) => {
+                let obj = v
+                    .as_object()
+                    .ok_or_else(|| Error::msg(format!("expected a JSON object, got {v}")))?;
+                fields
+                    .iter()
+                    .map(|f| {
+                        let v = obj
+                            .get(&f.id.to_string())
+                            .ok_or_else(|| Error::msg(format!("missing field {}", f.id)))?;
+                        Ok((f.id.clone(), IDLValue::from_json_with_type(v, env, &f.ty)?))
+                    })
+                    .collect::<Result<_>>()
+                    .map(IDLValue::Record)
+            }
+            Type::Variant(fields) => {
+                let obj = v
+                    .as_object()
+                    .ok_or_else(|| Error::msg(format!("expected a JSON object, got {v}")))?;
+                let (key, v) = obj
+                    .iter()
+                    .next()
+                    .ok_or_else(|| Error::msg("variant object has no fields"))?;
+                let field = fields
+                    .iter()
+                    .find(|f| &f.id.to_string() == key)
+                    .ok_or_else(|| Error::msg(format!("unknown variant tag {key}")))?;
+                Ok(IDLValue::Variant(Box::new((
+                    field.id.clone(),
+                    IDLValue::from_json_with_type(v, env, &field.ty)?,
+                ))))
+            }
+            other => Err(Error::msg(format!("cannot decode JSON as {other:?}"))),
+        }
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(v: &Value, what: &str) -> Result<T> {
+    expect_str(v, what)?
+        .parse::<T>()
+        .map_err(|_| Error::msg(format!("invalid {what} literal: {v}")))
+}
+
+fn parse_label(s: &str) -> Label {
+    s.parse::<u32>().map(Label::Unnamed).unwrap_or_else(|_| Label::Named(s.to_string()))
+}
+
+impl IDLArgs {
+    /// Convert every argument to JSON, as a JSON array. See
+    /// [`IDLValue::to_json`] for the per-value mapping and its caveats.
+    pub fn to_json(&self) -> Value {
+        Value::Array(self.args.iter().map(IDLValue::to_json).collect())
+    }
+
+    /// Convert from a JSON array such as [`IDLArgs::to_json`] produces,
+    /// decoding each element with [`IDLValue::from_json`] and its guesses.
+    pub fn from_json(v: &Value) -> Result<Self> {
+        let items = v
+            .as_array()
+            .ok_or_else(|| Error::msg(format!("expected a JSON array of arguments, got {v}")))?;
+        Ok(IDLArgs {
+            args: items.iter().map(IDLValue::from_json).collect::<Result<_>>()?,
+        })
+    }
+
+    /// Convert every argument to JSON using `types` (looked up in `env`) to
+    /// disambiguate number widths and recover field names from hashes.
+    pub fn to_json_with_types(&self, env: &TypeEnv, types: &[Type]) -> Result<Value> {
+        if types.len() != self.args.len() {
+            return Err(Error::msg("mismatched number of arguments and types"));
+        }
+        Ok(Value::Array(
+            self.args
+                .iter()
+                .zip(types)
+                .map(|(v, ty)| v.to_json_with_type(env, ty))
+                .collect::<Result<_>>()?,
+        ))
+    }
+
+    /// Convert from a JSON array using `types` (looked up in `env`), the
+    /// inverse of [`IDLArgs::to_json_with_types`].
+    pub fn from_json_with_types(v: &Value, env: &TypeEnv, types: &[Type]) -> Result<Self> {
+        let items = v
+            .as_array()
+            .ok_or_else(|| Error::msg(format!("expected a JSON array of arguments, got {v}")))?;
+        if items.len() != types.len() {
+            return Err(Error::msg("mismatched number of arguments and types"));
+        }
+        Ok(IDLArgs {
+            args: items
+                .iter()
+                .zip(types)
+                .map(|(v, ty)| IDLValue::from_json_with_type(v, env, ty))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::internal::Field;
+
+    #[test]
+    fn round_trips_record_variant_opt_and_blob_with_type() {
+        let ty = Type::Record(vec![
+            Field {
+                id: Label::Named("tag".to_string()),
+                ty: Type::Variant(vec![Field {
+                    id: Label::Named("ok".to_string()),
+                    ty: Type::Bool,
+                    comments: Default::default(),
+                }]),
+                comments: Default::default(),
+            },
+            Field {
+                id: Label::Named("note".to_string()),
+                ty: Type::Opt(Box::new(Type::Text)),
+                comments: Default::default(),
+            },
+            Field {
+                id: Label::Named("data".to_string()),
+                ty: Type::Vec(Box::new(Type::Nat8)),
+                comments: Default::default(),
+            },
+        ]);
+        let value = IDLValue::Record(vec![
+            (
+                Label::Named("tag".to_string()),
+                IDLValue::Variant(Box::new((Label::Named("ok".to_string()), IDLValue::Bool(true)))),
+            ),
+            (
+                Label::Named("note".to_string()),
+                IDLValue::Opt(Box::new(IDLValue::Text("hi".to_string()))),
+            ),
+            (Label::Named("data".to_string()), IDLValue::Blob(vec![1, 2, 3, 255])),
+        ]);
+
+        let env = TypeEnv::new();
+        let json = value.to_json_with_type(&env, &ty).unwrap();
+        let decoded = IDLValue::from_json_with_type(&json, &env, &ty).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn decode_hex_rejects_odd_length_input() {
+        assert!(decode_hex("0xabc").is_err());
+    }
+}
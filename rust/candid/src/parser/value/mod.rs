@@ -0,0 +1,354 @@
+//! The untyped representation of Candid values, [`IDLValue`], and a sequence
+//! of them, [`IDLArgs`].
+
+mod json;
+
+use crate::de::IDLDeserialize;
+use crate::error::{Error, Result};
+use crate::parser::typing::TypeEnv;
+use crate::ser::IDLBuilder;
+use crate::types::internal::{Field, Label, Type};
+use crate::types::number::{Int, Nat};
+use crate::types::principal::Principal;
+use crate::types::CandidType;
+use std::fmt;
+
+/// An untyped Candid value. Any value representable in the Candid wire
+/// format can be held here without knowing its Rust type ahead of time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IDLValue {
+    Bool(bool),
+    Null,
+    Text(String),
+    Principal(Principal),
+    Nat(Nat),
+    Int(Int),
+    Nat8(u8),
+    Nat16(u16),
+    Nat32(u32),
+    Nat64(u64),
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    Float32(f32),
+    Float64(f64),
+    Blob(Vec<u8>),
+    Opt(Box<IDLValue>),
+    None,
+    Vec(Vec<IDLValue>),
+    Record(Vec<(Label, IDLValue)>),
+    Variant(Box<(Label, IDLValue)>),
+}
+
+impl CandidType for IDLValue {
+    fn ty() -> Type {
+        // The concrete type of an `IDLValue` is only known once it carries
+        // an actual value; callers that need a static `Type` should go
+        // through `IDLArgs::to_bytes_with_types` instead.
+        Type::Reserved
+    }
+    fn to_idl_value(&self) -> IDLValue {
+        self.clone()
+    }
+    fn from_idl_value(v: IDLValue) -> Result<Self> {
+        Ok(v)
+    }
+}
+
+/// Infer the Candid [`Type`] that an [`IDLValue`] would have if it were
+/// encoded on its own, without a type annotation from a `.did` file.
+pub(crate) fn idl_value_to_type(v: &IDLValue) -> Type {
+    match v {
+        IDLValue::Bool(_) => Type::Bool,
+        IDLValue::Null | IDLValue::None => Type::Null,
+        IDLValue::Text(_) => Type::Text,
+        IDLValue::Principal(_) => Type::Principal,
+        IDLValue::Nat(_) => Type::Nat,
+        IDLValue::Int(_) => Type::Int,
+        IDLValue::Nat8(_) => Type::Nat8,
+        IDLValue::Nat16(_) => Type::Nat16,
+        IDLValue::Nat32(_) => Type::Nat32,
+        IDLValue::Nat64(_) => Type::Nat64,
+        IDLValue::Int8(_) => Type::Int8,
+        IDLValue::Int16(_) => Type::Int16,
+        IDLValue::Int32(_) => Type::Int32,
+        IDLValue::Int64(_) => Type::Int64,
+        IDLValue::Float32(_) => Type::Float32,
+        IDLValue::Float64(_) => Type::Float64,
+        IDLValue::Blob(_) => Type::Vec(Box::new(Type::Nat8)),
+        IDLValue::Opt(v) => Type::Opt(Box::new(idl_value_to_type(v))),
+        IDLValue::Vec(vs) => {
+            let elem = vs.first().map(idl_value_to_type).unwrap_or(Type::Null);
+            Type::Vec(Box::new(elem))
+        }
+        IDLValue::Record(fs) => {
+            let mut fields: Vec<Field> = fs
+                .iter()
+                .map(|(id, v)| Field {
+                    id: id.clone(),
+                    ty: idl_value_to_type(v),
+                    comments: Default::default(),
+                })
+                .collect();
+            fields.sort_by_key(|f| f.id.get_id());
+            Type::Record(fields)
+        }
+        IDLValue::Variant(v) => Type::Variant(vec![Field {
+            id: v.0.clone(),
+            ty: idl_value_to_type(&v.1),
+            comments: Default::default(),
+        }]),
+    }
+}
+
+/// A sequence of [`IDLValue`]s, corresponding to the arguments or return
+/// values of a Candid method call.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct IDLArgs {
+    pub args: Vec<IDLValue>,
+}
+
+impl IDLArgs {
+    pub fn new(args: &[IDLValue]) -> Self {
+        IDLArgs {
+            args: args.to_vec(),
+        }
+    }
+
+    /// Encode into the Candid binary format, inferring each value's type
+    /// from its runtime shape.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut builder = IDLBuilder::new();
+        for v in &self.args {
+            builder.value_arg(v)?;
+        }
+        builder.serialize_to_vec()
+    }
+
+    /// Encode into the Candid binary format at the given `types` instead of
+    /// types inferred from the values. Each value must already match its
+    /// type: numbers are not converted to the width a type names, and type
+    /// variables are not resolved through `env` (see
+    /// `IDLBuilder::value_arg_with_type`).
+    pub fn to_bytes_with_types(&self, env: &TypeEnv, types: &[Type]) -> Result<Vec<u8>> {
+        if types.len() != self.args.len() {
+            return Err(Error::msg("mismatched number of arguments and types"));
+        }
+        let mut builder = IDLBuilder::new();
+        for (v, ty) in self.args.iter().zip(types) {
+            builder.value_arg_with_type(v, env, ty)?;
+        }
+        builder.serialize_to_vec()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut de = IDLDeserialize::new(bytes)?;
+        let mut args = Vec::new();
+        while !de.is_done() {
+            args.push(de.get_value::<IDLValue>()?);
+        }
+        de.done()?;
+        Ok(IDLArgs { args })
+    }
+}
+
+impl fmt::Display for IDLArgs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for (i, v) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{v}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::str::FromStr for IDLArgs {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        crate::parser::types::parse_idl_args(s)
+    }
+}
+
+impl fmt::Display for IDLValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IDLValue::Bool(b) => write!(f, "{b}"),
+            IDLValue::Null | IDLValue::None => write!(f, "null"),
+            IDLValue::Text(s) => write!(f, "{s:?}"),
+            IDLValue::Principal(p) => write!(f, "principal \"{p}\""),
+            IDLValue::Nat(n) => write!(f, "{n}"),
+            IDLValue::Int(n) => write!(f, "{n}"),
+            IDLValue::Nat8(n) => write!(f, "{n}"),
+            IDLValue::Nat16(n) => write!(f, "{n}"),
+            IDLValue::Nat32(n) => write!(f, "{n}"),
+            IDLValue::Nat64(n) => write!(f, "{n}"),
+            IDLValue::Int8(n) => write!(f, "{n}"),
+            IDLValue::Int16(n) => write!(f, "{n}"),
+            IDLValue::Int32(n) => write!(f, "{n}"),
+            IDLValue::Int64(n) => write!(f, "{n}"),
+            IDLValue::Float32(n) => write!(f, "{n}"),
+            IDLValue::Float64(n) => write!(f, "{n}"),
+            IDLValue::Blob(b) => write!(f, "blob \"{}\"", hex(b)),
+            IDLValue::Opt(v) => write!(f, "opt {v}"),
+            IDLValue::Vec(vs) => {
+                write!(f, "vec {{")?;
+                for v in vs {
+                    write!(f, "{v};")?;
+                }
+                write!(f, "}}")
+            }
+            IDLValue::Record(fs) => {
+                write!(f, "record {{")?;
+                for (l, v) in fs {
+                    write!(f, "{l}={v};")?;
+                }
+                write!(f, "}}")
+            }
+            IDLValue::Variant(v) => write!(f, "variant {{ {}={} }}", v.0, v.1),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Parse a single Candid value literal, e.g. `opt record {label="text"; 42="haha"}`.
+///
+/// Number literals with no explicit width suffix are always parsed as
+/// [`IDLValue::Int`]; [`IDLArgs::to_bytes_with_types`] does not narrow them
+/// to a specific width.
+pub(crate) fn parse_idl_value(s: &str) -> Result<IDLValue> {
+    let s = s.trim();
+    if let Some(rest) = s.strip_prefix("opt ") {
+        return Ok(IDLValue::Opt(Box::new(parse_idl_value(rest)?)));
+    }
+    if let Some(rest) = s.strip_prefix("principal ") {
+        let text = rest.trim().trim_matches('"');
+        return Ok(IDLValue::Principal(Principal::from_slice(
+            text.as_bytes(),
+        )));
+    }
+    if let Some(rest) = s.strip_prefix("blob ") {
+        let text = rest.trim().trim_matches('"');
+        return Ok(IDLValue::Blob(text.as_bytes().to_vec()));
+    }
+    if let Some(body) = s.strip_prefix("vec ").map(str::trim_start) {
+        let body = body
+            .strip_prefix('{')
+            .and_then(|b| b.strip_suffix('}'))
+            .ok_or_else(|| Error::msg("expected `{ .. }` in vec literal"))?;
+        let vals = split_items(body)
+            .into_iter()
+            .map(|v| parse_idl_value(v.trim()))
+            .collect::<Result<_>>()?;
+        return Ok(IDLValue::Vec(vals));
+    }
+    if let Some(body) = s.strip_prefix("record").map(str::trim_start) {
+        let body = body
+            .strip_prefix('{')
+            .and_then(|b| b.strip_suffix('}'))
+            .ok_or_else(|| Error::msg("expected `{ .. }` in record literal"))?;
+        let fields = split_items(body)
+            .into_iter()
+            .map(|f| parse_field(f.trim()))
+            .collect::<Result<_>>()?;
+        return Ok(IDLValue::Record(fields));
+    }
+    if let Some(body) = s.strip_prefix("variant").map(str::trim_start) {
+        let body = body
+            .strip_prefix('{')
+            .and_then(|b| b.strip_suffix('}'))
+            .ok_or_else(|| Error::msg("expected `{ .. }` in variant literal"))?;
+        let (label, value) = parse_field(body.trim())?;
+        return Ok(IDLValue::Variant(Box::new((label, value))));
+    }
+    match s {
+        "true" => return Ok(IDLValue::Bool(true)),
+        "false" => return Ok(IDLValue::Bool(false)),
+        "null" => return Ok(IDLValue::Null),
+        _ => (),
+    }
+    if let Some(text) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        return Ok(IDLValue::Text(text.to_string()));
+    }
+    s.parse::<Int>()
+        .map(IDLValue::Int)
+        .map_err(|_| Error::msg(format!("cannot parse Candid value literal: {s}")))
+}
+
+fn parse_field(s: &str) -> Result<(Label, IDLValue)> {
+    let (label, value) = s
+        .split_once('=')
+        .ok_or_else(|| Error::msg("expected `label = value` in field"))?;
+    let label = label.trim();
+    let id = label
+        .parse::<u32>()
+        .map(Label::Unnamed)
+        .unwrap_or_else(|_| Label::Named(label.to_string()));
+    Ok((id, parse_idl_value(value.trim())?))
+}
+
+fn split_items(s: &str) -> Vec<&str> {
+    s.split(';').map(str::trim).filter(|s| !s.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Canonicalize a decoded value for comparison against the value it was
+    /// encoded from: sort record fields by hash (the wire format doesn't
+    /// preserve a record's original field order) and reduce every label to
+    /// its raw hash (decoding never recovers a `Label::Named` from the
+    /// wire, only the hash it was built from).
+    fn normalize(v: &IDLValue) -> IDLValue {
+        match v {
+            IDLValue::Record(fs) => {
+                let mut fs: Vec<_> = fs
+                    .iter()
+                    .map(|(l, v)| (Label::Id(l.get_id()), normalize(v)))
+                    .collect();
+                fs.sort_by_key(|(l, _)| l.get_id());
+                IDLValue::Record(fs)
+            }
+            IDLValue::Variant(v) => {
+                IDLValue::Variant(Box::new((Label::Id(v.0.get_id()), normalize(&v.1))))
+            }
+            IDLValue::Opt(v) => IDLValue::Opt(Box::new(normalize(v))),
+            IDLValue::Vec(vs) => IDLValue::Vec(vs.iter().map(normalize).collect()),
+            // `None` with no type annotation is inferred and decoded back
+            // as the untyped `null`; treat the two as equivalent here.
+            IDLValue::None => IDLValue::Null,
+            other => other.clone(),
+        }
+    }
+
+    #[test]
+    fn round_trips_record_variant_opt_and_blob() {
+        let args = IDLArgs::new(&[IDLValue::Record(vec![
+            (Label::Named("id".to_string()), IDLValue::Nat32(7)),
+            (
+                Label::Named("tag".to_string()),
+                IDLValue::Variant(Box::new((Label::Named("ok".to_string()), IDLValue::Bool(true)))),
+            ),
+            (
+                Label::Named("note".to_string()),
+                IDLValue::Opt(Box::new(IDLValue::Text("hi".to_string()))),
+            ),
+            (Label::Named("absent".to_string()), IDLValue::None),
+            (Label::Named("data".to_string()), IDLValue::Blob(vec![1, 2, 3, 255])),
+        ])]);
+        let bytes = args.to_bytes().unwrap();
+        let decoded = IDLArgs::from_bytes(&bytes).unwrap();
+        assert_eq!(args.args.len(), decoded.args.len());
+        for (a, b) in args.args.iter().zip(&decoded.args) {
+            assert_eq!(normalize(a), normalize(b));
+        }
+    }
+}
+
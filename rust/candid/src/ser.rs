@@ -0,0 +1,157 @@
+//! Serializing Rust values and [`IDLValue`]s into the Candid binary format.
+
+use crate::error::Result;
+use crate::parser::typing::TypeEnv;
+use crate::parser::value::{idl_value_to_type, IDLValue};
+use crate::types::internal::Type;
+use crate::types::CandidType;
+use crate::wire::{self, TypeTable};
+use std::io;
+
+/// A builder for a Candid message: a type table followed by a sequence of
+/// argument values. Arguments can be native Rust values (via [`arg`](Self::arg))
+/// or untyped [`IDLValue`]s (via [`value_arg`](Self::value_arg)), and the two
+/// can be mixed freely within the same message.
+#[derive(Default)]
+pub struct IDLBuilder {
+    types: Vec<Type>,
+    values: Vec<IDLValue>,
+}
+
+impl IDLBuilder {
+    pub fn new() -> Self {
+        IDLBuilder::default()
+    }
+
+    /// Add a native Rust value as the next argument.
+    pub fn arg<T: CandidType>(&mut self, value: &T) -> Result<&mut Self> {
+        self.types.push(T::ty());
+        self.values.push(value.to_idl_value());
+        Ok(self)
+    }
+
+    /// Add an untyped [`IDLValue`] as the next argument, inferring its type
+    /// from its runtime shape.
+    pub fn value_arg(&mut self, value: &IDLValue) -> Result<&mut Self> {
+        self.types.push(idl_value_to_type(value));
+        self.values.push(value.clone());
+        Ok(self)
+    }
+
+    /// Add an untyped [`IDLValue`] as the next argument, serialized at `ty`
+    /// instead of a type inferred from the value. Used by
+    /// [`IDLArgs::to_bytes_with_types`](crate::IDLArgs::to_bytes_with_types).
+    /// The value is written as it is: it must already match `ty` (e.g. an
+    /// `IDLValue::Nat8` for `nat8`), since numbers are not converted to the
+    /// width `ty` names, and `env` is not consulted, so a `Type::Var` in
+    /// `ty` is not resolved and such an argument fails to serialize.
+    pub(crate) fn value_arg_with_type(
+        &mut self,
+        value: &IDLValue,
+        _env: &TypeEnv,
+        ty: &Type,
+    ) -> Result<&mut Self> {
+        self.types.push(ty.clone());
+        self.values.push(value.clone());
+        Ok(self)
+    }
+
+    /// Serialize the collected arguments into a freshly allocated buffer.
+    pub fn serialize_to_vec(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.serialize(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Serialize the collected arguments directly to `w`.
+    ///
+    /// The wire format requires the type table to precede the value region,
+    /// so the table is still built and written up front. From there,
+    /// though, each argument's value bytes are written straight to `w`
+    /// instead of being buffered into an intermediate `Vec<u8>` first --
+    /// the piece that matters for streaming a large blob or vector to a
+    /// canister without holding the whole value region in memory.
+    pub fn serialize<W: io::Write>(&self, w: &mut W) -> Result<()> {
+        w.write_all(b"DIDL")?;
+        let (table, arg_refs) = TypeTable::build(&self.types);
+        table.write(w, &arg_refs)?;
+        for (ty, value) in self.types.iter().zip(&self.values) {
+            wire::write_value(w, ty, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Implemented for tuples of [`CandidType`] values, letting [`encode_args`]
+/// take a single tuple and fan it out into the builder's argument list.
+pub trait ArgumentEncoder {
+    fn idl_serialize(self, builder: &mut IDLBuilder) -> Result<()>;
+}
+
+macro_rules! tuple_encoder {
+    ($($t:ident),+) => {
+        impl<$($t: CandidType),+> ArgumentEncoder for ($($t,)+) {
+            #[allow(non_snake_case)]
+            fn idl_serialize(self, builder: &mut IDLBuilder) -> Result<()> {
+                let ($($t,)+) = self;
+                $(builder.arg(&$t)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+tuple_encoder!(A);
+tuple_encoder!(A, B);
+tuple_encoder!(A, B, C);
+tuple_encoder!(A, B, C, D);
+
+/// Encode a tuple of arguments into a Candid message.
+pub fn encode_args(args: impl ArgumentEncoder) -> Result<Vec<u8>> {
+    let mut builder = IDLBuilder::new();
+    args.idl_serialize(&mut builder)?;
+    builder.serialize_to_vec()
+}
+
+/// Encode a single argument into a Candid message.
+pub fn encode_one<T: CandidType>(arg: T) -> Result<Vec<u8>> {
+    let mut builder = IDLBuilder::new();
+    builder.arg(&arg)?;
+    builder.serialize_to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::{Codec, IDLDeserialize};
+
+    #[test]
+    fn builder_streams_a_tuple_argument_over_io_write() {
+        let mut builder = IDLBuilder::new();
+        builder.arg(&(1u32, "hi".to_string())).unwrap();
+        let mut buf = Vec::new();
+        builder.serialize(&mut buf).unwrap();
+
+        let mut de = IDLDeserialize::from_reader(std::io::Cursor::new(buf)).unwrap();
+        let decoded: (u32, String) = de.get_value().unwrap();
+        de.done().unwrap();
+        assert_eq!(decoded, (1, "hi".to_string()));
+    }
+
+    #[test]
+    fn codec_frames_a_single_value_over_io_read() {
+        let mut buf = Vec::new();
+        42u32.encode(&mut buf).unwrap();
+
+        let mut cursor = std::io::Cursor::new(buf);
+        assert_eq!(u32::decode(&mut cursor).unwrap(), 42u32);
+    }
+
+    #[test]
+    fn byte_vec_round_trips_through_the_blob_encoding() {
+        let blob: Vec<u8> = vec![0, 1, 2, 255, 254];
+        let bytes = encode_one(blob.clone()).unwrap();
+        let decoded: Vec<u8> = crate::de::decode_one(&bytes).unwrap();
+        assert_eq!(decoded, blob);
+    }
+}
@@ -0,0 +1,130 @@
+//! The internal representation of Candid types, as built by [`CandidType::ty`]
+//! and as parsed from a wire-format type table.
+
+use std::fmt;
+use std::rc::Rc;
+
+/// A field label, either a textual name or a raw hash for unnamed fields
+/// (e.g. tuple-style records and variants).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Label {
+    Named(String),
+    Id(u32),
+    Unnamed(u32),
+}
+
+impl Label {
+    /// The Candid hash of this label, as it appears on the wire.
+    pub fn get_id(&self) -> u32 {
+        match self {
+            Label::Named(name) => crate::idl_hash(name),
+            Label::Id(id) | Label::Unnamed(id) => *id,
+        }
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Label::Named(name) => write!(f, "{name}"),
+            Label::Id(id) | Label::Unnamed(id) => write!(f, "{id}"),
+        }
+    }
+}
+
+/// Comments written immediately around a declaration in a `.did` file:
+/// whole `//` or `/* .. */` lines directly above it, and a `//`/`/* .. */`
+/// comment trailing it on the same line. Used by the pretty printer to make
+/// parsing and re-printing a `.did` file idempotent.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Comments {
+    pub leading: Vec<String>,
+    pub trailing: Option<String>,
+}
+
+impl Comments {
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_none()
+    }
+}
+
+/// A single field of a `record` or `variant` type.
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub id: Label,
+    pub ty: Type,
+    pub comments: Comments,
+}
+
+/// Two fields are equal if their `id` and `ty` match; `comments` is
+/// source-formatting metadata and doesn't affect a type's identity.
+impl PartialEq for Field {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.ty == other.ty
+    }
+}
+
+/// A method signature in a service type.
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub args: Vec<Type>,
+    pub rets: Vec<Type>,
+    pub is_query: bool,
+    /// Comments attached to each entry of `args`/`rets`, in the same order.
+    /// The parser builds them with the same length as the `Vec<Type>` they
+    /// correspond to; nothing enforces that for a `Function` built by hand.
+    pub arg_comments: Vec<Comments>,
+    pub ret_comments: Vec<Comments>,
+}
+
+/// Two functions are equal if their signature matches; `arg_comments`/
+/// `ret_comments` is source-formatting metadata and doesn't affect a
+/// function's identity, the same as `Field`'s `comments`.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.args == other.args && self.rets == other.rets && self.is_query == other.is_query
+    }
+}
+
+/// The internal representation of a Candid type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Null,
+    Bool,
+    Nat,
+    Int,
+    Nat8,
+    Nat16,
+    Nat32,
+    Nat64,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Float32,
+    Float64,
+    Text,
+    Reserved,
+    Empty,
+    Principal,
+    Var(String),
+    Opt(Box<Type>),
+    Vec(Box<Type>),
+    Record(Vec<Field>),
+    Variant(Vec<Field>),
+    Service(Vec<(String, Function)>),
+    Func(Function),
+    /// A type that has already been resolved through a `TypeEnv`, shared so
+    /// that recursive types don't need to be cloned on every lookup.
+    Knot(Rc<Type>),
+}
+
+impl Type {
+    /// Look up a field of a `record`/`variant` type by its wire-format hash.
+    pub fn field_by_hash(&self, hash: u32) -> Option<&Field> {
+        match self {
+            Type::Record(fs) | Type::Variant(fs) => fs.iter().find(|f| f.id.get_id() == hash),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Types for representing Candid's type system and converting Rust values
+//! to and from it.
+
+pub mod internal;
+pub mod number;
+pub mod principal;
+pub mod reserved;
+
+pub use internal::{Comments, Field, Function, Label, Type};
+
+use crate::error::{Error, Result};
+use crate::parser::value::IDLValue;
+
+/// Implemented by any Rust type that can be described as a Candid type and
+/// converted to and from the untyped [`IDLValue`] representation that the
+/// serializer and deserializer operate on.
+///
+/// This is analogous to serde's `Serialize`/`Deserialize`, but also carries
+/// the static type information Candid needs to build the type table ahead
+/// of the value region.
+pub trait CandidType {
+    /// The Candid type corresponding to `Self`.
+    fn ty() -> Type;
+    /// Convert `self` into its untyped representation for encoding.
+    fn to_idl_value(&self) -> IDLValue;
+    /// Recover `Self` from its untyped representation after decoding.
+    fn from_idl_value(v: IDLValue) -> Result<Self>
+    where
+        Self: Sized;
+}
+
+macro_rules! primitive_impl {
+    ($t:ty, $ty:expr, $variant:ident) => {
+        impl CandidType for $t {
+            fn ty() -> Type {
+                $ty
+            }
+            fn to_idl_value(&self) -> IDLValue {
+                IDLValue::$variant(self.clone())
+            }
+            fn from_idl_value(v: IDLValue) -> Result<Self> {
+                match v {
+                    IDLValue::$variant(x) => Ok(x),
+                    other => Err(Error::msg(format!(
+                        "type mismatch: expected {:?}, got {:?}",
+                        $ty, other
+                    ))),
+                }
+            }
+        }
+    };
+}
+
+primitive_impl!(bool, Type::Bool, Bool);
+primitive_impl!(u8, Type::Nat8, Nat8);
+primitive_impl!(u16, Type::Nat16, Nat16);
+primitive_impl!(u32, Type::Nat32, Nat32);
+primitive_impl!(u64, Type::Nat64, Nat64);
+primitive_impl!(i8, Type::Int8, Int8);
+primitive_impl!(i16, Type::Int16, Int16);
+primitive_impl!(i32, Type::Int32, Int32);
+primitive_impl!(i64, Type::Int64, Int64);
+primitive_impl!(f32, Type::Float32, Float32);
+primitive_impl!(f64, Type::Float64, Float64);
+primitive_impl!(number::Int, Type::Int, Int);
+primitive_impl!(number::Nat, Type::Nat, Nat);
+primitive_impl!(principal::Principal, Type::Principal, Principal);
+
+impl CandidType for String {
+    fn ty() -> Type {
+        Type::Text
+    }
+    fn to_idl_value(&self) -> IDLValue {
+        IDLValue::Text(self.clone())
+    }
+    fn from_idl_value(v: IDLValue) -> Result<Self> {
+        match v {
+            IDLValue::Text(s) => Ok(s),
+            other => Err(Error::msg(format!("type mismatch: expected text, got {other:?}"))),
+        }
+    }
+}
+
+impl CandidType for &str {
+    fn ty() -> Type {
+        Type::Text
+    }
+    fn to_idl_value(&self) -> IDLValue {
+        IDLValue::Text((*self).to_string())
+    }
+    fn from_idl_value(_v: IDLValue) -> Result<Self> {
+        unreachable!("&str only appears on the encoding side; decode into String instead")
+    }
+}
+
+macro_rules! tuple_impl {
+    ($($t:ident => $i:expr),+) => {
+        impl<$($t: CandidType),+> CandidType for ($($t,)+) {
+            fn ty() -> Type {
+                Type::Record(vec![$(Field { id: Label::Id($i), ty: $t::ty(), comments: Default::default() }),+])
+            }
+            #[allow(non_snake_case)]
+            fn to_idl_value(&self) -> IDLValue {
+                let ($($t,)+) = self;
+                IDLValue::Record(vec![$((Label::Id($i), $t.to_idl_value())),+])
+            }
+            #[allow(non_snake_case)]
+            fn from_idl_value(v: IDLValue) -> Result<Self> {
+                let fields = match v {
+                    IDLValue::Record(fields) => fields,
+                    other => return Err(Error::msg(format!("type mismatch: expected record, got {other:?}"))),
+                };
+                $(
+                    let $t = $t::from_idl_value(
+                        fields
+                            .iter()
+                            .find(|(label, _)| label.get_id() == $i)
+                            .ok_or_else(|| Error::msg("missing tuple field"))?
+                            .1
+                            .clone(),
+                    )?;
+                )+
+                Ok(($($t,)+))
+            }
+        }
+    };
+}
+
+tuple_impl!(A => 0);
+tuple_impl!(A => 0, B => 1);
+tuple_impl!(A => 0, B => 1, C => 2);
+tuple_impl!(A => 0, B => 1, C => 2, D => 3);
+
+impl CandidType for () {
+    fn ty() -> Type {
+        Type::Null
+    }
+    fn to_idl_value(&self) -> IDLValue {
+        IDLValue::Null
+    }
+    fn from_idl_value(v: IDLValue) -> Result<Self> {
+        match v {
+            IDLValue::Null => Ok(()),
+            other => Err(Error::msg(format!("type mismatch: expected null, got {other:?}"))),
+        }
+    }
+}
+
+impl CandidType for reserved::Reserved {
+    fn ty() -> Type {
+        Type::Reserved
+    }
+    fn to_idl_value(&self) -> IDLValue {
+        IDLValue::Null
+    }
+    fn from_idl_value(_v: IDLValue) -> Result<Self> {
+        // `reserved` accepts and discards any well-formed value.
+        Ok(reserved::Reserved)
+    }
+}
+
+impl<T: CandidType> CandidType for Option<T> {
+    fn ty() -> Type {
+        Type::Opt(Box::new(T::ty()))
+    }
+    fn to_idl_value(&self) -> IDLValue {
+        match self {
+            Some(v) => IDLValue::Opt(Box::new(v.to_idl_value())),
+            None => IDLValue::None,
+        }
+    }
+    fn from_idl_value(v: IDLValue) -> Result<Self> {
+        match v {
+            IDLValue::Opt(inner) => Ok(Some(T::from_idl_value(*inner)?)),
+            IDLValue::None | IDLValue::Null => Ok(None),
+            other => Err(Error::msg(format!("type mismatch: expected opt, got {other:?}"))),
+        }
+    }
+}
+
+impl<T: CandidType> CandidType for Vec<T> {
+    fn ty() -> Type {
+        Type::Vec(Box::new(T::ty()))
+    }
+    fn to_idl_value(&self) -> IDLValue {
+        if T::ty() == Type::Nat8 {
+            // Round-trips through the compact `blob` encoding instead of
+            // collecting one `IDLValue::Nat8` per byte, which would blow up
+            // the memory footprint of encoding a large byte vector.
+            let bytes = self
+                .iter()
+                .map(|t| match t.to_idl_value() {
+                    IDLValue::Nat8(b) => b,
+                    _ => unreachable!("T::ty() == Type::Nat8"),
+                })
+                .collect();
+            return IDLValue::Blob(bytes);
+        }
+        IDLValue::Vec(self.iter().map(CandidType::to_idl_value).collect())
+    }
+    fn from_idl_value(v: IDLValue) -> Result<Self> {
+        match v {
+            IDLValue::Vec(vs) => vs.into_iter().map(T::from_idl_value).collect(),
+            IDLValue::Blob(bytes) if T::ty() == Type::Nat8 => {
+                // Byte vectors round-trip through the compact `blob` encoding.
+                bytes
+                    .into_iter()
+                    .map(|b| T::from_idl_value(IDLValue::Nat8(b)))
+                    .collect()
+            }
+            other => Err(Error::msg(format!("type mismatch: expected vec, got {other:?}"))),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Big integer types used to represent Candid's `int` and `nat`.
+
+use crate::error::{Error, Result};
+use num_bigint::{BigInt, BigUint};
+use std::fmt;
+
+/// Represents Candid type `int`, a signed integer of arbitrary precision.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Int(pub(crate) BigInt);
+
+/// Represents Candid type `nat`, an unsigned integer of arbitrary precision.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Nat(pub(crate) BigUint);
+
+impl Int {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        std::str::from_utf8(bytes)
+            .map_err(Error::msg)
+            .and_then(|s| s.parse())
+    }
+}
+
+impl std::str::FromStr for Int {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        s.parse::<BigInt>().map(Int).map_err(Error::msg)
+    }
+}
+
+impl std::str::FromStr for Nat {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        s.parse::<BigUint>().map(Nat).map_err(Error::msg)
+    }
+}
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Nat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u64> for Nat {
+    fn from(v: u64) -> Self {
+        Nat(BigUint::from(v))
+    }
+}
+
+impl From<i64> for Int {
+    fn from(v: i64) -> Self {
+        Int(BigInt::from(v))
+    }
+}
+
+impl std::ops::Add<u64> for Nat {
+    type Output = Nat;
+    fn add(self, rhs: u64) -> Nat {
+        Nat(self.0 + BigUint::from(rhs))
+    }
+}
@@ -0,0 +1,31 @@
+//! The `principal` type, identifying a canister or user on the Internet Computer.
+
+use std::fmt;
+
+/// An opaque identifier for a canister or user.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Principal(pub(crate) Vec<u8>);
+
+impl Principal {
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        Principal(bytes.to_vec())
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Textual encoding is base32 + CRC; omitted here as this crate only
+        // needs byte-level round-tripping for the features it implements.
+        write!(f, "{}", hex::encode(&self.0))
+    }
+}
+
+mod hex {
+    pub fn encode(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
@@ -0,0 +1,10 @@
+//! The `empty` and `reserved` Candid types.
+
+/// Represents Candid type `empty`. No value can be constructed for this type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Empty {}
+
+/// Represents Candid type `reserved`. Any value can be decoded as `Reserved`,
+/// and the decoded payload is simply discarded.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Reserved;
@@ -0,0 +1,597 @@
+//! The Candid wire format: the type table that precedes every message, and
+//! the value encoding that follows it.
+//!
+//! This is shared internal plumbing between [`crate::ser`] and [`crate::de`]
+//! so both the in-memory and streaming paths agree on a single encoding.
+
+use crate::error::{Error, Result};
+use crate::leb128;
+use crate::parser::value::IDLValue;
+use crate::types::internal::{Field, Label, Type};
+use crate::types::number::{Int, Nat};
+use crate::types::principal::Principal;
+use num_bigint::{BigInt, BigUint};
+use num_traits::{ToPrimitive, Zero};
+use std::io::{Read, Write};
+
+const OPCODE_OPT: i64 = -18;
+const OPCODE_VEC: i64 = -19;
+const OPCODE_RECORD: i64 = -20;
+const OPCODE_VARIANT: i64 = -21;
+
+fn primitive_opcode(ty: &Type) -> Option<i64> {
+    Some(match ty {
+        Type::Null => -1,
+        Type::Bool => -2,
+        Type::Nat => -3,
+        Type::Int => -4,
+        Type::Nat8 => -5,
+        Type::Nat16 => -6,
+        Type::Nat32 => -7,
+        Type::Nat64 => -8,
+        Type::Int8 => -9,
+        Type::Int16 => -10,
+        Type::Int32 => -11,
+        Type::Int64 => -12,
+        Type::Float32 => -13,
+        Type::Float64 => -14,
+        Type::Text => -15,
+        Type::Reserved => -16,
+        Type::Empty => -17,
+        Type::Principal => -24,
+        _ => return None,
+    })
+}
+
+fn primitive_from_opcode(op: i64) -> Type {
+    match op {
+        -1 => Type::Null,
+        -2 => Type::Bool,
+        -3 => Type::Nat,
+        -4 => Type::Int,
+        -5 => Type::Nat8,
+        -6 => Type::Nat16,
+        -7 => Type::Nat32,
+        -8 => Type::Nat64,
+        -9 => Type::Int8,
+        -10 => Type::Int16,
+        -11 => Type::Int32,
+        -12 => Type::Int64,
+        -13 => Type::Float32,
+        -14 => Type::Float64,
+        -15 => Type::Text,
+        -17 => Type::Empty,
+        -24 => Type::Principal,
+        _ => Type::Reserved,
+    }
+}
+
+enum TableEntry {
+    Opt(i64),
+    Vec(i64),
+    Record(Vec<(u32, i64)>),
+    Variant(Vec<(u32, i64)>),
+}
+
+/// A type table being assembled for a set of argument types, ready to be
+/// written to the wire ahead of the value region.
+#[derive(Default)]
+pub(crate) struct TypeTable {
+    entries: Vec<TableEntry>,
+}
+
+impl TypeTable {
+    /// Intern `ty` into the table (recursively, for compound types) and
+    /// return its wire-format type reference.
+    fn intern(&mut self, ty: &Type) -> i64 {
+        if let Some(op) = primitive_opcode(ty) {
+            return op;
+        }
+        match ty {
+            Type::Opt(inner) => {
+                let r = self.intern(inner);
+                self.entries.push(TableEntry::Opt(r));
+                (self.entries.len() - 1) as i64
+            }
+            Type::Vec(inner) => {
+                let r = self.intern(inner);
+                self.entries.push(TableEntry::Vec(r));
+                (self.entries.len() - 1) as i64
+            }
+            Type::Record(fields) => {
+                let refs = fields
+                    .iter()
+                    .map(|f| (f.id.get_id(), self.intern(&f.ty)))
+                    .collect();
+                self.entries.push(TableEntry::Record(refs));
+                (self.entries.len() - 1) as i64
+            }
+            Type::Variant(fields) => {
+                let refs = fields
+                    .iter()
+                    .map(|f| (f.id.get_id(), self.intern(&f.ty)))
+                    .collect();
+                self.entries.push(TableEntry::Variant(refs));
+                (self.entries.len() - 1) as i64
+            }
+            // Services, functions and unresolved type variables aren't
+            // needed by the value codec this crate implements; callers
+            // never construct values of those types.
+            Type::Var(_) | Type::Knot(_) | Type::Service(_) | Type::Func(_) => -16,
+            // Every primitive type returns early via `primitive_opcode` above.
+            Type::Null
+            | Type::Bool
+            | Type::Nat
+            | Type::Int
+            | Type::Nat8
+            | Type::Nat16
+            | Type::Nat32
+            | Type::Nat64
+            | Type::Int8
+            | Type::Int16
+            | Type::Int32
+            | Type::Int64
+            | Type::Float32
+            | Type::Float64
+            | Type::Text
+            | Type::Reserved
+            | Type::Empty
+            | Type::Principal => unreachable!(),
+        }
+    }
+
+    /// Build the table for a list of argument types, returning the table
+    /// together with each argument's top-level type reference.
+    pub(crate) fn build(tys: &[Type]) -> (Self, Vec<i64>) {
+        let mut table = TypeTable::default();
+        let refs = tys.iter().map(|ty| table.intern(ty)).collect();
+        (table, refs)
+    }
+
+    pub(crate) fn write<W: Write>(&self, w: &mut W, arg_refs: &[i64]) -> Result<()> {
+        leb128::write_unsigned(w, self.entries.len() as u64)?;
+        for entry in &self.entries {
+            match entry {
+                TableEntry::Opt(r) => {
+                    leb128::write_signed(w, OPCODE_OPT)?;
+                    leb128::write_signed(w, *r)?;
+                }
+                TableEntry::Vec(r) => {
+                    leb128::write_signed(w, OPCODE_VEC)?;
+                    leb128::write_signed(w, *r)?;
+                }
+                TableEntry::Record(fields) => {
+                    leb128::write_signed(w, OPCODE_RECORD)?;
+                    leb128::write_unsigned(w, fields.len() as u64)?;
+                    for (id, r) in fields {
+                        leb128::write_unsigned(w, *id as u64)?;
+                        leb128::write_signed(w, *r)?;
+                    }
+                }
+                TableEntry::Variant(fields) => {
+                    leb128::write_signed(w, OPCODE_VARIANT)?;
+                    leb128::write_unsigned(w, fields.len() as u64)?;
+                    for (id, r) in fields {
+                        leb128::write_unsigned(w, *id as u64)?;
+                        leb128::write_signed(w, *r)?;
+                    }
+                }
+            }
+        }
+        leb128::write_unsigned(w, arg_refs.len() as u64)?;
+        for r in arg_refs {
+            leb128::write_signed(w, *r)?;
+        }
+        Ok(())
+    }
+}
+
+/// A type table that has been read off the wire, plus the argument type
+/// references that follow it. Resolving a reference into a [`Type`] is done
+/// lazily by [`WireTypeTable::resolve`] instead of up front, since most readers
+/// only ever need a handful of the declared argument types.
+pub(crate) struct WireTypeTable {
+    entries: Vec<TableEntry>,
+    pub(crate) arg_refs: Vec<i64>,
+}
+
+impl WireTypeTable {
+    pub(crate) fn read<R: Read>(r: &mut R) -> Result<Self> {
+        let len = leb128::read_unsigned(r)? as usize;
+        let mut entries = Vec::with_capacity(len);
+        for _ in 0..len {
+            let opcode = leb128::read_signed(r)?;
+            entries.push(match opcode {
+                OPCODE_OPT => TableEntry::Opt(leb128::read_signed(r)?),
+                OPCODE_VEC => TableEntry::Vec(leb128::read_signed(r)?),
+                OPCODE_RECORD => TableEntry::Record(Self::read_fields(r)?),
+                OPCODE_VARIANT => TableEntry::Variant(Self::read_fields(r)?),
+                other => return Err(Error::msg(format!("unsupported type table opcode {other}"))),
+            });
+        }
+        let arg_len = leb128::read_unsigned(r)? as usize;
+        let mut arg_refs = Vec::with_capacity(arg_len);
+        for _ in 0..arg_len {
+            arg_refs.push(leb128::read_signed(r)?);
+        }
+        Ok(WireTypeTable { entries, arg_refs })
+    }
+
+    fn read_fields<R: Read>(r: &mut R) -> Result<Vec<(u32, i64)>> {
+        let len = leb128::read_unsigned(r)? as usize;
+        let mut fields = Vec::with_capacity(len);
+        for _ in 0..len {
+            let id = leb128::read_unsigned(r)? as u32;
+            let r = leb128::read_signed(r)?;
+            fields.push((id, r));
+        }
+        Ok(fields)
+    }
+
+    /// Expand a type reference back into a concrete [`Type`], failing
+    /// instead of panicking if `r` (or any reference it transitively leads
+    /// to) points past the end of the table -- a malformed or adversarial
+    /// message can declare such a reference.
+    pub(crate) fn resolve(&self, r: i64) -> Result<Type> {
+        if r < 0 {
+            return Ok(primitive_from_opcode(r));
+        }
+        let entry = self
+            .entries
+            .get(r as usize)
+            .ok_or_else(|| Error::msg(format!("type table reference {r} out of range")))?;
+        Ok(match entry {
+            TableEntry::Opt(inner) => Type::Opt(Box::new(self.resolve(*inner)?)),
+            TableEntry::Vec(inner) => Type::Vec(Box::new(self.resolve(*inner)?)),
+            TableEntry::Record(fields) => Type::Record(
+                fields
+                    .iter()
+                    .map(|(id, r)| {
+                        Ok(Field {
+                            id: Label::Id(*id),
+                            ty: self.resolve(*r)?,
+                            comments: Default::default(),
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            TableEntry::Variant(fields) => Type::Variant(
+                fields
+                    .iter()
+                    .map(|(id, r)| {
+                        Ok(Field {
+                            id: Label::Id(*id),
+                            ty: self.resolve(*r)?,
+                            comments: Default::default(),
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+pub(crate) fn write_value<W: Write>(w: &mut W, ty: &Type, v: &IDLValue) -> Result<()> {
+    match (ty, v) {
+        (Type::Null, _) | (Type::Reserved, _) | (Type::Empty, _) => Ok(()),
+        (Type::Bool, IDLValue::Bool(b)) => Ok(w.write_all(&[*b as u8])?),
+        (Type::Nat8, IDLValue::Nat8(n)) => Ok(w.write_all(&[*n])?),
+        (Type::Nat16, IDLValue::Nat16(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Nat32, IDLValue::Nat32(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Nat64, IDLValue::Nat64(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Int8, IDLValue::Int8(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Int16, IDLValue::Int16(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Int32, IDLValue::Int32(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Int64, IDLValue::Int64(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Float32, IDLValue::Float32(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Float64, IDLValue::Float64(n)) => Ok(w.write_all(&n.to_le_bytes())?),
+        (Type::Nat, IDLValue::Nat(n)) => write_nat(w, n),
+        (Type::Int, IDLValue::Int(n)) => write_int(w, n),
+        (Type::Text, IDLValue::Text(s)) => {
+            leb128::write_unsigned(w, s.len() as u64)?;
+            Ok(w.write_all(s.as_bytes())?)
+        }
+        (Type::Principal, IDLValue::Principal(p)) => {
+            leb128::write_unsigned(w, p.as_slice().len() as u64)?;
+            Ok(w.write_all(p.as_slice())?)
+        }
+        (Type::Opt(inner), IDLValue::Opt(v)) => {
+            w.write_all(&[1])?;
+            write_value(w, inner, v)
+        }
+        (Type::Opt(_), IDLValue::None) | (Type::Opt(_), IDLValue::Null) => Ok(w.write_all(&[0])?),
+        (Type::Vec(inner), IDLValue::Blob(bytes)) if **inner == Type::Nat8 => {
+            leb128::write_unsigned(w, bytes.len() as u64)?;
+            Ok(w.write_all(bytes)?)
+        }
+        (Type::Vec(inner), IDLValue::Vec(vs)) => {
+            leb128::write_unsigned(w, vs.len() as u64)?;
+            for v in vs {
+                write_value(w, inner, v)?;
+            }
+            Ok(())
+        }
+        (Type::Record(fields), IDLValue::Record(vs)) => {
+            for f in fields {
+                let (_, val) = vs
+                    .iter()
+                    .find(|(id, _)| id.get_id() == f.id.get_id())
+                    .ok_or_else(|| Error::msg(format!("missing field {}", f.id)))?;
+                write_value(w, &f.ty, val)?;
+            }
+            Ok(())
+        }
+        (Type::Variant(fields), IDLValue::Variant(v)) => {
+            let idx = fields
+                .iter()
+                .position(|f| f.id.get_id() == v.0.get_id())
+                .ok_or_else(|| Error::msg(format!("unknown variant tag {}", v.0)))?;
+            leb128::write_unsigned(w, idx as u64)?;
+            write_value(w, &fields[idx].ty, &v.1)
+        }
+        (ty, v) => Err(Error::msg(format!("value {v} does not match type {ty:?}"))),
+    }
+}
+
+pub(crate) fn read_value<R: Read>(r: &mut R, ty: &Type) -> Result<IDLValue> {
+    Ok(match ty {
+        Type::Null | Type::Reserved | Type::Empty => IDLValue::Null,
+        Type::Bool => IDLValue::Bool(read_u8(r)? != 0),
+        Type::Nat8 => IDLValue::Nat8(read_u8(r)?),
+        Type::Nat16 => IDLValue::Nat16(u16::from_le_bytes(read_n(r)?)),
+        Type::Nat32 => IDLValue::Nat32(u32::from_le_bytes(read_n(r)?)),
+        Type::Nat64 => IDLValue::Nat64(u64::from_le_bytes(read_n(r)?)),
+        Type::Int8 => IDLValue::Int8(i8::from_le_bytes(read_n(r)?)),
+        Type::Int16 => IDLValue::Int16(i16::from_le_bytes(read_n(r)?)),
+        Type::Int32 => IDLValue::Int32(i32::from_le_bytes(read_n(r)?)),
+        Type::Int64 => IDLValue::Int64(i64::from_le_bytes(read_n(r)?)),
+        Type::Float32 => IDLValue::Float32(f32::from_le_bytes(read_n(r)?)),
+        Type::Float64 => IDLValue::Float64(f64::from_le_bytes(read_n(r)?)),
+        Type::Nat => IDLValue::Nat(read_nat(r)?),
+        Type::Int => IDLValue::Int(read_int(r)?),
+        Type::Text => {
+            let len = leb128::read_unsigned(r)? as usize;
+            let mut buf = vec![0u8; len];
+            r.read_exact(&mut buf)?;
+            IDLValue::Text(String::from_utf8(buf).map_err(Error::msg)?)
+        }
+        Type::Principal => {
+            let len = leb128::read_unsigned(r)? as usize;
+            let mut buf = vec![0u8; len];
+            r.read_exact(&mut buf)?;
+            IDLValue::Principal(Principal::from_slice(&buf))
+        }
+        Type::Opt(inner) => {
+            if read_u8(r)? == 0 {
+                IDLValue::None
+            } else {
+                IDLValue::Opt(Box::new(read_value(r, inner)?))
+            }
+        }
+        Type::Vec(inner) => {
+            let len = leb128::read_unsigned(r)? as usize;
+            if **inner == Type::Nat8 {
+                let mut buf = vec![0u8; len];
+                r.read_exact(&mut buf)?;
+                IDLValue::Blob(buf)
+            } else {
+                let mut vs = Vec::with_capacity(len);
+                for _ in 0..len {
+                    vs.push(read_value(r, inner)?);
+                }
+                IDLValue::Vec(vs)
+            }
+        }
+        Type::Record(fields) => {
+            let mut vs = Vec::with_capacity(fields.len());
+            for f in fields {
+                vs.push((f.id.clone(), read_value(r, &f.ty)?));
+            }
+            IDLValue::Record(vs)
+        }
+        Type::Variant(fields) => {
+            let idx = leb128::read_unsigned(r)? as usize;
+            let f = fields
+                .get(idx)
+                .ok_or_else(|| Error::msg("variant index out of range"))?;
+            IDLValue::Variant(Box::new((f.id.clone(), read_value(r, &f.ty)?)))
+        }
+        Type::Var(_) | Type::Knot(_) | Type::Service(_) | Type::Func(_) => {
+            return Err(Error::msg("cannot decode a value of this type"))
+        }
+    })
+}
+
+/// Advance past a value of type `ty` without materializing it, using the
+/// type table to know how many bytes to discard. This is what lets a reader
+/// skip sibling fields it isn't interested in.
+pub(crate) fn skip_value<R: Read>(r: &mut R, ty: &Type) -> Result<()> {
+    match ty {
+        Type::Null | Type::Reserved | Type::Empty => Ok(()),
+        Type::Bool | Type::Nat8 | Type::Int8 => skip_bytes(r, 1),
+        Type::Nat16 | Type::Int16 => skip_bytes(r, 2),
+        Type::Nat32 | Type::Int32 | Type::Float32 => skip_bytes(r, 4),
+        Type::Nat64 | Type::Int64 | Type::Float64 => skip_bytes(r, 8),
+        Type::Nat => {
+            read_nat(r)?;
+            Ok(())
+        }
+        Type::Int => {
+            read_int(r)?;
+            Ok(())
+        }
+        Type::Text | Type::Principal => {
+            let len = leb128::read_unsigned(r)? as usize;
+            skip_bytes(r, len)
+        }
+        Type::Opt(inner) => {
+            if read_u8(r)? == 0 {
+                Ok(())
+            } else {
+                skip_value(r, inner)
+            }
+        }
+        Type::Vec(inner) => {
+            let len = leb128::read_unsigned(r)? as usize;
+            if **inner == Type::Nat8 {
+                skip_bytes(r, len)
+            } else {
+                for _ in 0..len {
+                    skip_value(r, inner)?;
+                }
+                Ok(())
+            }
+        }
+        Type::Record(fields) => {
+            for f in fields {
+                skip_value(r, &f.ty)?;
+            }
+            Ok(())
+        }
+        Type::Variant(fields) => {
+            let idx = leb128::read_unsigned(r)? as usize;
+            let f = fields
+                .get(idx)
+                .ok_or_else(|| Error::msg("variant index out of range"))?;
+            skip_value(r, &f.ty)
+        }
+        Type::Var(_) | Type::Knot(_) | Type::Service(_) | Type::Func(_) => {
+            Err(Error::msg("cannot skip a value of this type"))
+        }
+    }
+}
+
+/// Decode the value addressed by `path` inside a value of type `ty`,
+/// discarding every other field along the way with [`skip_value`] instead
+/// of decoding it -- including the fields of a record that come after the
+/// one `path` leads through, so the reader ends up past the *whole* value,
+/// not just the part that was looked up. Transparently unwraps any number
+/// of `opt` layers, the same way a record/variant field would be addressed
+/// if it weren't wrapped in one. An empty `path` decodes `ty` in full, like
+/// [`read_value`].
+///
+/// This is what lets [`crate::de::IDLDeserialize::get_field`] decode a
+/// single nested field of a large argument without materializing the
+/// whole thing, while leaving the stream correctly positioned at the next
+/// argument afterwards.
+pub(crate) fn extract_field<R: Read>(r: &mut R, ty: &Type, path: &[&str]) -> Result<IDLValue> {
+    let label = match path.first() {
+        None => return read_value(r, ty),
+        Some(label) => label,
+    };
+    match ty {
+        Type::Opt(inner) => {
+            if read_u8(r)? == 0 {
+                Err(Error::msg(format!("field {label} is absent (null opt)")))
+            } else {
+                extract_field(r, inner, path)
+            }
+        }
+        Type::Record(fields) => {
+            let hash = crate::idl_hash(label);
+            let mut found = None;
+            for f in fields {
+                if f.id.get_id() == hash {
+                    found = Some(extract_field(r, &f.ty, &path[1..])?);
+                } else {
+                    skip_value(r, &f.ty)?;
+                }
+            }
+            found.ok_or_else(|| Error::msg(format!("record has no field named {label}")))
+        }
+        Type::Variant(fields) => {
+            let hash = crate::idl_hash(label);
+            let idx = leb128::read_unsigned(r)? as usize;
+            let f = fields
+                .get(idx)
+                .ok_or_else(|| Error::msg("variant index out of range"))?;
+            if f.id.get_id() == hash {
+                extract_field(r, &f.ty, &path[1..])
+            } else {
+                Err(Error::msg(format!(
+                    "variant is tagged {}, not {label}",
+                    f.id
+                )))
+            }
+        }
+        other => Err(Error::msg(format!(
+            "cannot look up field {label} in a value of type {other:?}"
+        ))),
+    }
+}
+
+fn skip_bytes<R: Read>(r: &mut R, len: usize) -> Result<()> {
+    std::io::copy(&mut (&mut *r).take(len as u64), &mut std::io::sink())?;
+    Ok(())
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8> {
+    let buf: [u8; 1] = read_n(r)?;
+    Ok(buf[0])
+}
+
+fn read_n<R: Read, const N: usize>(r: &mut R) -> Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn write_nat<W: Write>(w: &mut W, n: &Nat) -> Result<()> {
+    let mut value = n.0.clone();
+    loop {
+        let mut byte = (&value & BigUint::from(0x7fu8)).to_u8().unwrap();
+        value >>= 7;
+        if !value.is_zero() {
+            byte |= 0x80;
+        }
+        w.write_all(&[byte])?;
+        if value.is_zero() {
+            return Ok(());
+        }
+    }
+}
+
+fn read_nat<R: Read>(r: &mut R) -> Result<Nat> {
+    let mut result = BigUint::zero();
+    let mut shift = 0usize;
+    loop {
+        let byte = read_u8(r)?;
+        result |= BigUint::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(Nat(result));
+        }
+        shift += 7;
+    }
+}
+
+fn write_int<W: Write>(w: &mut W, n: &Int) -> Result<()> {
+    let mut value = n.0.clone();
+    loop {
+        let byte = (&value & BigInt::from(0x7f)).to_i64().unwrap() as u8;
+        value >>= 7;
+        let done = (value.is_zero() && byte & 0x40 == 0)
+            || (value == BigInt::from(-1) && byte & 0x40 != 0);
+        w.write_all(&[if done { byte } else { byte | 0x80 }])?;
+        if done {
+            return Ok(());
+        }
+    }
+}
+
+fn read_int<R: Read>(r: &mut R) -> Result<Int> {
+    let mut result = BigInt::zero();
+    let mut shift = 0usize;
+    let mut byte;
+    loop {
+        byte = read_u8(r)?;
+        result |= BigInt::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    if byte & 0x40 != 0 {
+        result -= BigInt::from(1) << shift;
+    }
+    Ok(Int(result))
+}